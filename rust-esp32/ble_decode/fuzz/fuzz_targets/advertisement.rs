@@ -0,0 +1,28 @@
+#![no_main]
+
+//! Whole advertisement payloads, as received by the scanner
+
+use ble_decode::{find_local_name, parse_ad, DecoderRegistry, Decryptor, Device, FrameTracker};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let decryptor = Decryptor::from_devices([Device {
+        mac: "A4:C1:38:4E:2D:5C".to_string(),
+        key: "000102030405060708090a0b0c0d0e0f".to_string(),
+        room: "Fuzz".to_string(),
+        model: None,
+    }])
+    .unwrap();
+    let mac = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+    let _ = decryptor.decode_frame_data(data);
+    let _ = decryptor.decode_atc_frame(mac, data);
+    let _ = decryptor.decode_bthome_frame(mac, data);
+    // every registered format, Ruuvi, Govee, Qingping, SwitchBot and Inkbird included
+    let registry = DecoderRegistry::with_defaults(&decryptor);
+    let _ = registry.decode_advertisement(None, data);
+    let _ = registry.decode_advertisement(Some(mac), data);
+    let _ = FrameTracker::new().check_advertisement(mac, data);
+    let _ = find_local_name(data);
+    for _ in parse_ad(data) {}
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|s: &str| {
+    if let Ok(bytes) = ble_decode::decode_hex(s) {
+        assert_eq!(bytes.len() * 2, s.len());
+    }
+});
@@ -0,0 +1,50 @@
+#![no_main]
+
+//! Service and manufacturer data of each format, with keys so the decryption paths are reached
+
+use ble_decode::{
+    decode_atc, decode_object, decrypt_atc, decrypt_bthome, parse_bthome, parse_govee,
+    parse_inkbird, parse_mibeacon, parse_qingping, parse_ruuvi, parse_switchbot, GoveeModel,
+};
+use libfuzzer_sys::fuzz_target;
+
+const KEY: [u8; 16] = [0; 16];
+const LEGACY_KEY: [u8; 12] = [0; 12];
+const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frame) = parse_mibeacon(data) {
+        let _ = frame.decrypt(&KEY);
+        let _ = frame.decrypt(&LEGACY_KEY);
+        let _ = decode_object(frame.object);
+    }
+    let _ = decode_object(data);
+
+    let _ = decode_atc(data);
+    let _ = decrypt_atc(data, MAC, &KEY);
+
+    if let Ok(frame) = parse_bthome(data) {
+        let _ = frame.measurements();
+    }
+    let _ = decrypt_bthome(data, MAC, &KEY);
+
+    if let Ok(frame) = parse_ruuvi(data) {
+        let _ = frame.measurements();
+    }
+    for model in GoveeModel::ALL {
+        let _ = parse_govee(model, data);
+    }
+    let _ = parse_qingping(data);
+
+    // SwitchBot: service data, then manufacturer data after the first byte as length
+    let _ = parse_switchbot(data, None);
+    if let Some((&len, rest)) = data.split_first() {
+        let (service_data, manufacturer_data) = rest.split_at((len as usize).min(rest.len()));
+        let _ = parse_switchbot(service_data, Some(manufacturer_data));
+    }
+
+    // Inkbird: the company id holds the temperature
+    if let [a, b, rest @ ..] = data {
+        let _ = parse_inkbird(u16::from_le_bytes([*a, *b]), rest);
+    }
+});
@@ -0,0 +1,177 @@
+//! BLE advertising data, a list of AD structures : length (1 byte) | type (1 byte) | data
+
+use std::borrow::Cow;
+
+const AD_FLAGS: u8 = 0x01;
+const AD_SHORT_LOCAL_NAME: u8 = 0x08;
+const AD_COMPLETE_LOCAL_NAME: u8 = 0x09;
+const AD_TX_POWER: u8 = 0x0A;
+const AD_SERVICE_DATA_16: u8 = 0x16;
+const AD_MANUFACTURER_DATA: u8 = 0xFF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdRecord<'a> {
+    Flags(u8),
+    ShortLocalName(Cow<'a, str>),
+    CompleteLocalName(Cow<'a, str>),
+    /// Service data of a 16 bits UUID
+    ServiceData {
+        uuid: u16,
+        data: &'a [u8],
+    },
+    ManufacturerData {
+        company_id: u16,
+        data: &'a [u8],
+    },
+    /// dBm
+    TxPower(i8),
+    Other {
+        ad_type: u8,
+        data: &'a [u8],
+    },
+}
+
+impl AdRecord<'_> {
+    fn parse(ad_type: u8, data: &[u8]) -> AdRecord<'_> {
+        match (ad_type, data) {
+            (AD_FLAGS, [flags]) => AdRecord::Flags(*flags),
+            (AD_SHORT_LOCAL_NAME, _) => AdRecord::ShortLocalName(String::from_utf8_lossy(data)),
+            (AD_COMPLETE_LOCAL_NAME, _) => {
+                AdRecord::CompleteLocalName(String::from_utf8_lossy(data))
+            }
+            (AD_TX_POWER, [power]) => AdRecord::TxPower(*power as i8),
+            (AD_SERVICE_DATA_16, [a, b, data @ ..]) => AdRecord::ServiceData {
+                uuid: u16::from_le_bytes([*a, *b]),
+                data,
+            },
+            (AD_MANUFACTURER_DATA, [a, b, data @ ..]) => AdRecord::ManufacturerData {
+                company_id: u16::from_le_bytes([*a, *b]),
+                data,
+            },
+            _ => AdRecord::Other { ad_type, data },
+        }
+    }
+
+    /// Short or complete local name
+    pub fn local_name(&self) -> Option<&str> {
+        match self {
+            AdRecord::ShortLocalName(name) | AdRecord::CompleteLocalName(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the AD structures of an advertisement payload
+///
+/// Stops at the first zero length (padding) or truncated structure.
+#[derive(Debug, Clone)]
+pub struct AdStructures<'a> {
+    data: &'a [u8],
+}
+
+pub fn parse_ad(data: &[u8]) -> AdStructures<'_> {
+    AdStructures { data }
+}
+
+impl<'a> Iterator for AdStructures<'a> {
+    type Item = AdRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&len, rest) = self.data.split_first()?;
+        let len = len as usize;
+
+        if len == 0 || rest.len() < len {
+            self.data = &[];
+            return None;
+        }
+
+        let (structure, next) = rest.split_at(len);
+        self.data = next;
+
+        Some(AdRecord::parse(structure[0], &structure[1..]))
+    }
+}
+
+/// Service data of the 16 bits `uuid` in an advertisement payload
+pub fn find_service_data(data: &[u8], uuid: u16) -> Option<&[u8]> {
+    parse_ad(data).find_map(|record| match record {
+        AdRecord::ServiceData { uuid: u, data } if u == uuid => Some(data),
+        _ => None,
+    })
+}
+
+/// Manufacturer specific data of `company_id` in an advertisement payload
+pub fn find_manufacturer_data(data: &[u8], company_id: u16) -> Option<&[u8]> {
+    parse_ad(data).find_map(|record| match record {
+        AdRecord::ManufacturerData {
+            company_id: c,
+            data,
+        } if c == company_id => Some(data),
+        _ => None,
+    })
+}
+
+/// Local name in an advertisement payload
+pub fn find_local_name(data: &[u8]) -> Option<Cow<'_, str>> {
+    parse_ad(data).find_map(|record| match record {
+        AdRecord::ShortLocalName(name) | AdRecord::CompleteLocalName(name) => Some(name),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn records() {
+        let data = decode_hex(concat!(
+            "020106",                   // flags
+            "0B094C5957534430334D4D43", // complete local name
+            "020AF4",                   // tx power
+            "051695FE0102",             // service data
+            "06FF99040512FC",           // manufacturer data
+            "0220FE",                   // unhandled type
+            "0000",                     // padding
+        ))
+        .unwrap();
+
+        let records: Vec<AdRecord> = parse_ad(&data).collect();
+
+        assert_eq!(
+            records,
+            vec![
+                AdRecord::Flags(0x06),
+                AdRecord::CompleteLocalName("LYWSD03MMC".into()),
+                AdRecord::TxPower(-12),
+                AdRecord::ServiceData {
+                    uuid: 0xFE95,
+                    data: &[0x01, 0x02]
+                },
+                AdRecord::ManufacturerData {
+                    company_id: 0x0499,
+                    data: &[0x05, 0x12, 0xFC]
+                },
+                AdRecord::Other {
+                    ad_type: 0x20,
+                    data: &[0xFE]
+                },
+            ]
+        );
+        assert_eq!(records[1].local_name(), Some("LYWSD03MMC"));
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(data in vec(any::<u8>(), 0..64)) {
+            for record in parse_ad(&data) {
+                let _ = record.local_name();
+            }
+            let _ = find_service_data(&data, 0xFE95);
+            let _ = find_manufacturer_data(&data, 0x0499);
+            let _ = find_local_name(&data);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! ATC1441 / pvvx custom firmware advertisements (service data UUID 0x181A)
+//!
+//! The format is told by the service data length:
+//!
+//! ```text
+//! ATC1441            (13) : MAC (BE) | temp i16 BE 0.1°C | humi u8 % | batt u8 % | batt u16 BE mV | cnt
+//! pvvx custom        (15) : MAC (LE) | temp i16 0.01°C | humi u16 0.01% | batt u16 mV | batt u8 % | cnt | flags
+//! ATC1441 encrypted   (8) : cnt | temp u8 0.5°C - 40 | humi u8 0.5% | batt u8 % (bit 7 trigger) | MIC
+//! pvvx encrypted     (11) : cnt | temp i16 0.01°C | humi u16 0.01% | batt u8 % | flags | MIC
+//! ```
+//!
+//! Encrypted formats use AES-CCM with an 11 bytes nonce: MAC (LE) followed by the first 5 bytes
+//! of the AD structure (length, type, UUID, counter).
+
+use ccm::{
+    aead::{generic_array::GenericArray, Aead, Payload},
+    consts::{U11, U4},
+    Ccm,
+};
+
+use aes::Aes128;
+
+use crate::{
+    BatteryPercent, DecodeError, DeviceKey, Measurement, RelativeHumidity, Temperature, Voltage,
+};
+
+/// 0x181A Environmental Sensing
+pub const ATC_SERVICE_UUID: u16 = 0x181A;
+
+const ATC1441_LEN: usize = 13;
+const CUSTOM_LEN: usize = 15;
+const ATC1441_ENCRYPTED_LEN: usize = 8;
+const CUSTOM_ENCRYPTED_LEN: usize = 11;
+
+type AtcCcm = Ccm<Aes128, U4, U11>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtcFormat {
+    Atc1441,
+    Custom,
+    Atc1441Encrypted,
+    CustomEncrypted,
+}
+
+impl AtcFormat {
+    pub fn from_len(len: usize) -> Option<Self> {
+        match len {
+            ATC1441_LEN => Some(AtcFormat::Atc1441),
+            CUSTOM_LEN => Some(AtcFormat::Custom),
+            ATC1441_ENCRYPTED_LEN => Some(AtcFormat::Atc1441Encrypted),
+            CUSTOM_ENCRYPTED_LEN => Some(AtcFormat::CustomEncrypted),
+            _ => None,
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(
+            self,
+            AtcFormat::Atc1441Encrypted | AtcFormat::CustomEncrypted
+        )
+    }
+}
+
+/// A decoded custom firmware advertisement
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtcFrame {
+    pub format: AtcFormat,
+    /// Device MAC in display order, only sent by the plain formats
+    pub mac: Option<[u8; 6]>,
+    pub counter: u8,
+    pub measurements: Vec<Measurement>,
+}
+
+/// Decode plain ATC1441 / pvvx custom service data
+pub fn decode_atc(data: &[u8]) -> Result<AtcFrame, DecodeError> {
+    let format = AtcFormat::from_len(data.len()).ok_or(DecodeError::UnknownFormat)?;
+
+    match format {
+        AtcFormat::Atc1441 => {
+            let mac = [data[0], data[1], data[2], data[3], data[4], data[5]];
+            let temperature = i16::from_be_bytes([data[6], data[7]]);
+
+            Ok(AtcFrame {
+                format,
+                mac: Some(mac),
+                counter: data[12],
+                measurements: vec![
+                    Measurement::TemperatureHumidity {
+                        temperature: Temperature::from_decidegrees(temperature),
+                        humidity: RelativeHumidity::from_decipercent(data[8] as u16 * 10),
+                    },
+                    Measurement::Battery(BatteryPercent::new(data[9])),
+                    Measurement::Voltage(Voltage::from_millivolts(u16::from_be_bytes([
+                        data[10], data[11],
+                    ]))),
+                ],
+            })
+        }
+        AtcFormat::Custom => {
+            let mac = [data[5], data[4], data[3], data[2], data[1], data[0]];
+            let temperature = i16::from_le_bytes([data[6], data[7]]);
+            let humidity = u16::from_le_bytes([data[8], data[9]]);
+
+            Ok(AtcFrame {
+                format,
+                mac: Some(mac),
+                counter: data[13],
+                measurements: vec![
+                    Measurement::TemperatureHumidity {
+                        temperature: Temperature::from_centidegrees(temperature),
+                        humidity: RelativeHumidity::from_centipercent(humidity),
+                    },
+                    Measurement::Battery(BatteryPercent::new(data[12])),
+                    Measurement::Voltage(Voltage::from_millivolts(u16::from_le_bytes([
+                        data[10], data[11],
+                    ]))),
+                ],
+            })
+        }
+        AtcFormat::Atc1441Encrypted | AtcFormat::CustomEncrypted => Err(DecodeError::Encrypted),
+    }
+}
+
+/// Decrypt and decode encrypted pvvx service data, `mac` being the advertiser address
+pub fn decrypt_atc(data: &[u8], mac: [u8; 6], key: &[u8]) -> Result<AtcFrame, DecodeError> {
+    decrypt_atc_with(data, mac, &DeviceKey::new(key)?)
+}
+
+/// Same as `decrypt_atc` with an already parsed key
+pub fn decrypt_atc_with(
+    data: &[u8],
+    mac: [u8; 6],
+    key: &DeviceKey,
+) -> Result<AtcFrame, DecodeError> {
+    let format = AtcFormat::from_len(data.len()).ok_or(DecodeError::UnknownFormat)?;
+    if !format.is_encrypted() {
+        return Err(DecodeError::Unencrypted);
+    }
+
+    let uuid = ATC_SERVICE_UUID.to_le_bytes();
+    let nonce = [
+        mac[5],
+        mac[4],
+        mac[3],
+        mac[2],
+        mac[1],
+        mac[0],
+        data.len() as u8 + 3,
+        0x16,
+        uuid[0],
+        uuid[1],
+        data[0],
+    ];
+
+    let cipher: AtcCcm = key.ccm()?;
+    let plain = cipher
+        .decrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: &data[1..],
+                aad: &[0x11],
+            },
+        )
+        .map_err(|_| DecodeError::TagMismatch)?;
+
+    let measurements = match (format, plain.as_slice()) {
+        (AtcFormat::Atc1441Encrypted, [temperature, humidity, battery]) => vec![
+            Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(*temperature as i16 * 5 - 400),
+                humidity: RelativeHumidity::from_decipercent(*humidity as u16 * 5),
+            },
+            Measurement::Battery(BatteryPercent::new(battery & 0x7F)),
+        ],
+        (AtcFormat::CustomEncrypted, [t0, t1, h0, h1, battery, _flags]) => vec![
+            Measurement::TemperatureHumidity {
+                temperature: Temperature::from_centidegrees(i16::from_le_bytes([*t0, *t1])),
+                humidity: RelativeHumidity::from_centipercent(u16::from_le_bytes([*h0, *h1])),
+            },
+            Measurement::Battery(BatteryPercent::new(*battery)),
+        ],
+        _ => return Err(DecodeError::FrameTooShort),
+    };
+
+    Ok(AtcFrame {
+        format,
+        mac: None,
+        counter: data[0],
+        measurements,
+    })
+}
+
+/// Encrypted service data of a custom format `plain` payload: counter | payload | MIC, for tests
+#[cfg(test)]
+pub(crate) fn encrypt_atc(plain: &[u8], counter: u8, mac: [u8; 6], key: &[u8]) -> Vec<u8> {
+    use ccm::aead::KeyInit;
+
+    // counter | payload | MIC
+    let len = 1 + plain.len() + 4;
+    let uuid = ATC_SERVICE_UUID.to_le_bytes();
+    let nonce = [
+        mac[5],
+        mac[4],
+        mac[3],
+        mac[2],
+        mac[1],
+        mac[0],
+        len as u8 + 3,
+        0x16,
+        uuid[0],
+        uuid[1],
+        counter,
+    ];
+    let encrypted = AtcCcm::new_from_slice(key)
+        .unwrap()
+        .encrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: plain,
+                aad: &[0x11],
+            },
+        )
+        .unwrap();
+
+    [&[counter][..], &encrypted].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x02, 0x83, 0xF4];
+
+    #[test]
+    fn atc1441() {
+        let frame = decode_atc(&decode_hex("a4c1380283f400a22f5f0bf819").unwrap()).unwrap();
+
+        assert_eq!(frame.format, AtcFormat::Atc1441);
+        assert_eq!(frame.mac, Some(MAC));
+        assert_eq!(frame.counter, 0x19);
+        assert_eq!(
+            frame.measurements,
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(162),
+                    humidity: RelativeHumidity::from_decipercent(470),
+                },
+                Measurement::Battery(BatteryPercent::new(95)),
+                Measurement::Voltage(Voltage::from_millivolts(3064)),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom() {
+        let frame = decode_atc(&decode_hex("f4830238c1a4a9066911b60b58f70d").unwrap()).unwrap();
+
+        assert_eq!(frame.format, AtcFormat::Custom);
+        assert_eq!(frame.mac, Some(MAC));
+        assert_eq!(frame.counter, 0xF7);
+        assert_eq!(
+            frame.measurements,
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(171),
+                    humidity: RelativeHumidity::from_decipercent(446),
+                },
+                Measurement::Battery(BatteryPercent::new(88)),
+                Measurement::Voltage(Voltage::from_millivolts(2998)),
+            ]
+        );
+    }
+
+    #[test]
+    fn encrypted() {
+        let key = decode_hex("b9ea895fac7eea6d30532432a516f3a3").unwrap();
+
+        // -5.5°C 60% 77%, trigger bit set
+        let data = encrypt_atc(&[0x45, 0x78, 0xCD], 0x20, MAC, &key);
+        assert_eq!(decode_atc(&data), Err(DecodeError::Encrypted));
+
+        let frame = decrypt_atc(&data, MAC, &key).unwrap();
+        assert_eq!(frame.format, AtcFormat::Atc1441Encrypted);
+        assert_eq!(frame.counter, 0x20);
+        assert_eq!(
+            frame.measurements,
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(-55),
+                    humidity: RelativeHumidity::from_decipercent(600),
+                },
+                Measurement::Battery(BatteryPercent::new(77)),
+            ]
+        );
+
+        // -12.34°C 45.67%
+        let data = encrypt_atc(
+            &decode_hex("2efbd71152000000").unwrap()[..6],
+            0x21,
+            MAC,
+            &key,
+        );
+        let frame = decrypt_atc(&data, MAC, &key).unwrap();
+        assert_eq!(frame.format, AtcFormat::CustomEncrypted);
+        assert_eq!(
+            frame.measurements[0],
+            Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(-123),
+                humidity: RelativeHumidity::from_decipercent(457),
+            }
+        );
+
+        let mut tampered = data.clone();
+        tampered[1] ^= 0x01;
+        assert_eq!(
+            decrypt_atc(&tampered, MAC, &key),
+            Err(DecodeError::TagMismatch)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(data in vec(any::<u8>(), 0..20), mac in any::<[u8; 6]>()) {
+            let _ = decode_atc(&data);
+            let _ = decrypt_atc(&data, mac, &[0; 16]);
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//! Decode captured advertisements, one hex frame per line, printing one JSON object per frame
+//!
+//! ```text
+//! ble-decode [--devices registry.json|toml] [--watch] [FILE]
+//! ```
+//!
+//! Lines hold the advertisement payload in hex, with or without spaces, optionally prefixed by
+//! the advertiser MAC (needed for the ATC / BTHome encrypted formats). Quotes, commas and `//`
+//! comments are ignored so frames can be pasted from the tests.
+//!
+//! btsnoop (btmon) and pcap (Wireshark) captures are also accepted as FILE, their advertising
+//! reports are decoded with the capture timestamp and RSSI.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process, thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use ble_decode::{
+    atc::ATC_SERVICE_UUID, bthome::BTHOME_SERVICE_UUID, decode_atc, decode_hex, find_service_data,
+    format_mac, is_capture, mibeacon::MIBEACON_SERVICE_UUID, parse_bthome, parse_mac,
+    parse_mibeacon, product, read_capture, AtcFormat, DecodeError, DecoderRegistry, Decryptor,
+    Measurement, MiBeaconFrame,
+};
+use serde::Serialize;
+
+const USAGE: &str = "Usage: ble-decode [--devices <registry.json|toml>] [--watch] [FILE]
+
+Reads hex frames from FILE, or stdin when missing or `-`. FILE can also be a btsnoop or pcap
+capture.
+
+Options:
+  -d, --devices <PATH>  Device registry holding the bind keys
+  -w, --watch           Keep reading FILE as it grows, like `tail -f`
+  -h, --help            Print this help";
+
+/// Delay between two reads of a followed file at its end
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    devices: Option<PathBuf>,
+    watch: bool,
+    input: Option<PathBuf>,
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> anyhow::Result<Option<Args>> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-d" | "--devices" => {
+                let path = args.next().context("--devices needs a path")?;
+                parsed.devices = Some(path.into());
+            }
+            "-w" | "--watch" => parsed.watch = true,
+            "-" => parsed.input = None,
+            _ if arg.starts_with('-') => bail!("unknown option {}", arg),
+            _ if parsed.input.is_some() => bail!("only one input file is supported"),
+            _ => parsed.input = Some(arg.into()),
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+/// One decoded line, or capture advertisement
+#[derive(Debug, Default, Serialize)]
+struct Output {
+    line: usize,
+    /// Capture time, seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rssi: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counter: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    measurements: Vec<Measurement>,
+    /// Decoded, but not as expected from the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A frame read from a line: optional advertiser MAC and payload
+#[derive(Debug, PartialEq)]
+struct Line {
+    mac: Option<[u8; 6]>,
+    data: Vec<u8>,
+}
+
+/// `None` for blank and comment lines
+fn parse_line(line: &str) -> Option<Result<Line, String>> {
+    let line = line.split("//").next().unwrap_or_default().trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let mut tokens: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '"')
+        .filter(|token| !token.is_empty())
+        .collect();
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let mac = match parse_mac(tokens[0]) {
+        Some(mac) => {
+            tokens.remove(0);
+            Some(mac)
+        }
+        None => None,
+    };
+
+    let hex = tokens.concat();
+    let data = decode_hex(&hex).map_err(|e| format!("invalid hex frame ({}): {}", e, hex));
+
+    Some(data.map(|data| Line { mac, data }))
+}
+
+/// The decryptor for the MiBeacon frames details, and the registry for every format
+struct Decoders {
+    decryptor: Decryptor,
+    registry: DecoderRegistry,
+}
+
+impl Decoders {
+    fn new(decryptor: Decryptor) -> Self {
+        let registry = DecoderRegistry::with_defaults(&decryptor);
+        Decoders {
+            decryptor,
+            registry,
+        }
+    }
+}
+
+/// Frame headers telling the model, MAC or counter, looked up in the registry order
+enum Headers<'a> {
+    MiBeacon(MiBeaconFrame<'a>),
+    Atc(&'a [u8]),
+    BtHome(&'a [u8]),
+}
+
+impl<'a> Headers<'a> {
+    fn of(data: &'a [u8]) -> Option<Self> {
+        if let Some(service_data) = find_service_data(data, MIBEACON_SERVICE_UUID) {
+            return parse_mibeacon(service_data).ok().map(Headers::MiBeacon);
+        }
+        find_service_data(data, ATC_SERVICE_UUID)
+            .map(Headers::Atc)
+            .or_else(|| find_service_data(data, BTHOME_SERVICE_UUID).map(Headers::BtHome))
+    }
+}
+
+fn decode(decoders: &Decoders, line: &Line) -> Output {
+    let data = &line.data;
+    let mut output = Output {
+        mac: line.mac.map(format_mac),
+        ..Output::default()
+    };
+
+    let headers = Headers::of(data);
+    if let Some(Headers::MiBeacon(frame)) = &headers {
+        describe_mibeacon(frame, &mut output);
+        match decoders.decryptor.decode_frame(line.mac, data) {
+            Ok(decoded) => {
+                output.warning = decoded.is_unexpected().then(|| {
+                    format!(
+                        "unexpected object 0x{:04X} for {}",
+                        decoded.object_id,
+                        decoded.model().unwrap_or_default()
+                    )
+                });
+                output.measurements = vec![decoded.measurement];
+            }
+            Err(e) => output.error = Some(e.to_string()),
+        }
+        return output;
+    }
+
+    let Some(decoder) = decoders.registry.decoder(data) else {
+        output.error = Some(DecodeError::UnknownFormat.to_string());
+        return output;
+    };
+    output.model = Some(decoder.name().to_string());
+    match headers {
+        Some(Headers::Atc(service_data)) => describe_atc(service_data, &mut output),
+        Some(Headers::BtHome(service_data)) => describe_bthome(service_data, &mut output),
+        Some(Headers::MiBeacon(_)) | None => {}
+    }
+
+    match decoder.decode(line.mac, data) {
+        Ok(measurements) => output.measurements = measurements,
+        Err(e) => output.error = Some(e.to_string()),
+    }
+    output
+}
+
+/// Model, MAC and counter of the MiBeacon frame header
+fn describe_mibeacon(frame: &MiBeaconFrame, output: &mut Output) {
+    output.model = Some(match product(frame.product_id) {
+        Some(product) => product.model.to_string(),
+        None => format!("MiBeacon 0x{:04X}", frame.product_id),
+    });
+    output.counter = Some(match frame.ext_counter {
+        Some(ext) => u32::from_le_bytes([frame.frame_counter, ext[0], ext[1], ext[2]]),
+        None => frame.frame_counter as u32,
+    });
+    if let Some(mac) = frame.mac {
+        output.mac = Some(format_mac(mac));
+    }
+}
+
+/// ATC format, MAC and counter, the encrypted formats only telling the counter
+fn describe_atc(service_data: &[u8], output: &mut Output) {
+    let Some(format) = AtcFormat::from_len(service_data.len()) else {
+        return;
+    };
+    output.model = Some(format!("{:?}", format));
+    if format.is_encrypted() {
+        output.counter = Some(service_data[0] as u32);
+    } else if let Ok(frame) = decode_atc(service_data) {
+        output.mac = frame.mac.map(format_mac).or(output.mac.take());
+        output.counter = Some(frame.counter as u32);
+    }
+}
+
+/// BTHome counter of the encrypted frames, packet id of the plain ones
+fn describe_bthome(service_data: &[u8], output: &mut Output) {
+    output.counter = match parse_bthome(service_data) {
+        // counter | MIC at the end of the service data
+        Err(DecodeError::Encrypted) => service_data
+            .len()
+            .checked_sub(8)
+            .map(|start| &service_data[start..start + 4])
+            .map(|counter| u32::from_le_bytes([counter[0], counter[1], counter[2], counter[3]])),
+        Ok(frame) => frame.packet_id().map(|id| id as u32),
+        Err(_) => None,
+    };
+}
+
+fn is_capture_file(path: &Path) -> io::Result<bool> {
+    let mut magic = Vec::with_capacity(8);
+    File::open(path)?.take(8).read_to_end(&mut magic)?;
+
+    Ok(is_capture(&magic))
+}
+
+fn decode_capture(decoders: &Decoders, path: &Path) -> anyhow::Result<()> {
+    let capture = std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let advertisements = read_capture(&capture)
+        .with_context(|| format!("cannot read capture {}", path.display()))?;
+
+    for (index, advertisement) in advertisements.into_iter().enumerate() {
+        let line = Line {
+            mac: Some(advertisement.mac),
+            data: advertisement.data,
+        };
+        let output = Output {
+            line: index + 1,
+            timestamp: Some(advertisement.timestamp.as_secs_f64()),
+            rssi: advertisement.rssi,
+            ..decode(decoders, &line)
+        };
+        println!("{}", serde_json::to_string(&output)?);
+    }
+
+    Ok(())
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let decryptor = match &args.devices {
+        Some(path) => Decryptor::from_path(path)
+            .with_context(|| format!("cannot load registry {}", path.display()))?,
+        None => Decryptor::empty(),
+    };
+    let decoders = Decoders::new(decryptor);
+
+    if let Some(path) = &args.input {
+        if is_capture_file(path).with_context(|| format!("cannot open {}", path.display()))? {
+            if args.watch {
+                bail!("--watch only applies to text logs");
+            }
+            return decode_capture(&decoders, path);
+        }
+    }
+
+    let mut input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).with_context(|| format!("cannot open {}", path.display()))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    };
+    // stdin already blocks for more input
+    let follow = args.watch && args.input.is_some();
+
+    let mut buffer = String::new();
+    let mut line_number = 0;
+    loop {
+        let read = input.read_line(&mut buffer)?;
+        if read == 0 || (follow && !buffer.ends_with('\n')) {
+            if !follow {
+                break;
+            }
+            // wait for the end of a partially written line
+            thread::sleep(WATCH_INTERVAL);
+            continue;
+        }
+
+        line_number += 1;
+        let output = match parse_line(&buffer) {
+            None => None,
+            Some(Ok(line)) => Some(Output {
+                line: line_number,
+                ..decode(&decoders, &line)
+            }),
+            Some(Err(error)) => Some(Output {
+                line: line_number,
+                error: Some(error),
+                ..Output::default()
+            }),
+        };
+        buffer.clear();
+
+        if let Some(output) = output {
+            println!("{}", serde_json::to_string(&output)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("ble-decode: {:#}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ble_decode::{Device, Temperature};
+
+    #[test]
+    fn args() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args(&[]).unwrap(), Some(Args::default()));
+        assert_eq!(
+            args(&["-d", "devices.toml", "--watch", "frames.log"]).unwrap(),
+            Some(Args {
+                devices: Some("devices.toml".into()),
+                watch: true,
+                input: Some("frames.log".into()),
+            })
+        );
+        assert_eq!(args(&["--help"]).unwrap(), None);
+        assert!(args(&["--devices"]).is_err());
+        assert!(args(&["--verbose"]).is_err());
+        assert!(args(&["a.log", "b.log"]).is_err());
+    }
+
+    #[test]
+    fn lines() {
+        let expected = Line {
+            mac: None,
+            data: vec![0x02, 0x01, 0x06],
+        };
+
+        assert_eq!(parse_line("020106"), Some(Ok(expected)));
+        assert_eq!(
+            parse_line(r#"  "02 01 06", // 23.3°C 56%"#)
+                .unwrap()
+                .unwrap()
+                .data,
+            vec![0x02, 0x01, 0x06]
+        );
+        assert_eq!(
+            parse_line("A4:C1:38:4E:2D:5C 020106").unwrap().unwrap().mac,
+            Some([0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C])
+        );
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("# captured on the balcony"), None);
+        assert_eq!(parse_line("// 26.7°C 93%"), None);
+        assert!(parse_line("02010").unwrap().is_err());
+        assert!(parse_line("0201é6").unwrap().is_err());
+    }
+
+    #[test]
+    fn outputs() {
+        let decryptor = Decryptor::from_devices([Device {
+            mac: "A4:C1:38:4E:2D:5C".to_string(),
+            key: "000102030405060708090a0b0c0d0e0f".to_string(),
+            room: "Salon".to_string(),
+            model: None,
+        }])
+        .unwrap();
+        let decoders = Decoders::new(decryptor.clone());
+        let decode_line = |line: &str| decode(&decoders, &parse_line(line).unwrap().unwrap());
+
+        let measurement = Measurement::Temperature(Temperature::from_decidegrees(236));
+        let device = decryptor.devices().next().unwrap();
+        let frame = ble_decode::encode_frame(device, 0x0102, &measurement).unwrap();
+        let output = decode_line(&ble_decode::encode_hex(&frame));
+
+        assert_eq!(output.mac.as_deref(), Some("A4:C1:38:4E:2D:5C"));
+        assert_eq!(output.model.as_deref(), Some("LYWSD03MMC"));
+        assert_eq!(output.warning, None);
+        assert_eq!(output.counter, Some(0x0102));
+        assert_eq!(output.measurements, vec![measurement]);
+        assert_eq!(output.error, None);
+
+        // without MAC in the frame, the address prefix is used to decrypt it
+        let mut frame = frame;
+        frame.drain(12..18);
+        frame[3] -= 6;
+        frame[7] &= !0x10;
+        let output = decode_line(&format!(
+            "A4:C1:38:4E:2D:5C {}",
+            ble_decode::encode_hex(&frame).replace(' ', "")
+        ));
+        assert_eq!(output.mac.as_deref(), Some("A4:C1:38:4E:2D:5C"));
+        assert_eq!(output.measurements, vec![measurement]);
+
+        // plain illuminance object, not sent by the LYWSD03MMC
+        let output = decode_line("0E1695FE40505B0501071003640000");
+        assert_eq!(output.model.as_deref(), Some("LYWSD03MMC"));
+        assert_eq!(
+            output.warning.as_deref(),
+            Some("unexpected object 0x1007 for LYWSD03MMC")
+        );
+        assert_eq!(output.measurements.len(), 1);
+
+        // MJWSD05MMC temperature and battery objects of the MiBeacon v2 object ids
+        for line in [
+            "0F1695FE4050322801014C0466663E41",
+            "0C1695FE40503228010348015F",
+        ] {
+            let output = decode_line(line);
+            assert_eq!(output.model.as_deref(), Some("MJWSD05MMC"));
+            assert_eq!(output.warning, None);
+            assert_eq!(output.error, None);
+            assert_eq!(output.measurements.len(), 1);
+        }
+
+        let output = decode_line("0201061A1695FE58585B054F112233445566F553717C49000000ED75FC9D");
+        assert_eq!(output.mac.as_deref(), Some("66:55:44:33:22:11"));
+        assert_eq!(
+            output.error.as_deref(),
+            Some("unknown device 66:55:44:33:22:11")
+        );
+
+        let output = decode_line("02010610161A18a4c1380283f400a22f5f0bf819");
+        assert_eq!(output.model.as_deref(), Some("Atc1441"));
+        assert_eq!(output.mac.as_deref(), Some("A4:C1:38:02:83:F4"));
+        assert_eq!(output.measurements.len(), 3);
+
+        let output = decode_line("0201060A16D2FC4002CA0903BF13");
+        assert_eq!(output.model.as_deref(), Some("BTHome"));
+        assert_eq!(output.measurements.len(), 2);
+
+        assert_eq!(
+            serde_json::to_string(&decode_line("020106")).unwrap(),
+            r#"{"line":0,"error":"unknown frame format"}"#
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Merge the BLE keys printed by Xiaomi-cloud-tokens-extractor into a device registry
+//!
+//! ```text
+//! ./run_docker.sh | tee tokens.txt
+//! ble-import --devices src/devices.json tokens.txt
+//! ```
+
+use std::{
+    io::{self, Read},
+    path::PathBuf,
+    process,
+};
+
+use anyhow::{bail, Context};
+use ble_decode::{
+    format_devices,
+    import::{merge_devices, parse_extractor_output},
+    load_devices, save_devices,
+};
+
+const USAGE: &str = "Usage: ble-import --devices <registry.json|toml> [--dry-run] [FILE]
+
+Reads the Xiaomi-cloud-tokens-extractor output from FILE, or stdin when missing or `-`, and
+merges its BLE devices into the registry, created when missing.
+
+Options:
+  -d, --devices <PATH>  Device registry to update
+  -n, --dry-run         Print the merged registry instead of writing it, in the same format
+  -h, --help            Print this help";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    devices: PathBuf,
+    dry_run: bool,
+    input: Option<PathBuf>,
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> anyhow::Result<Option<Args>> {
+    let mut devices = None;
+    let mut dry_run = false;
+    let mut input = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-d" | "--devices" => {
+                devices = Some(args.next().context("--devices needs a path")?.into());
+            }
+            "-n" | "--dry-run" => dry_run = true,
+            "-" => input = None,
+            _ if arg.starts_with('-') => bail!("unknown option {}", arg),
+            _ if input.is_some() => bail!("only one input file is supported"),
+            _ => input = Some(arg.into()),
+        }
+    }
+
+    Ok(Some(Args {
+        devices: devices.context("--devices is required")?,
+        dry_run,
+        input,
+    }))
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let text = match &args.input {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+
+    let mut registry = if args.devices.exists() {
+        load_devices(&args.devices)
+            .with_context(|| format!("cannot load registry {}", args.devices.display()))?
+    } else {
+        Vec::new()
+    };
+
+    let summary = merge_devices(&mut registry, &parse_extractor_output(&text));
+
+    for mac in &summary.added {
+        eprintln!("added     {}", mac);
+    }
+    for mac in &summary.updated {
+        eprintln!("updated   {}", mac);
+    }
+    for mac in &summary.unchanged {
+        eprintln!("unchanged {}", mac);
+    }
+    for (name, reason) in &summary.skipped {
+        eprintln!("skipped   {} : {}", name, reason);
+    }
+
+    if args.dry_run {
+        // previewed in the format the registry would be saved in
+        print!("{}", format_devices(&args.devices, &registry)?);
+    } else if !summary.added.is_empty() || !summary.updated.is_empty() {
+        save_devices(&args.devices, &registry)
+            .with_context(|| format!("cannot write registry {}", args.devices.display()))?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("ble-import: {:#}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(
+            args(&["-d", "devices.toml", "-n", "tokens.txt"]).unwrap(),
+            Some(Args {
+                devices: "devices.toml".into(),
+                dry_run: true,
+                input: Some("tokens.txt".into()),
+            })
+        );
+        assert_eq!(args(&["--help"]).unwrap(), None);
+        assert!(args(&["tokens.txt"]).is_err());
+        assert!(args(&["-d", "devices.json", "--force"]).is_err());
+    }
+}
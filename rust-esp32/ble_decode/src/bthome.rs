@@ -0,0 +1,587 @@
+//! BTHome v2 advertisements (service data UUID 0xFCD2)
+//!
+//! ```text
+//! Device info | Objects : id (1 byte) | value (fixed length by id) ...
+//!      1      |                          n
+//! ```
+//!
+//! Encrypted frames append a 4 bytes counter and the 4 bytes MIC. The AES-CCM nonce is the MAC
+//! (display order), the UUID, the device info byte and the counter.
+
+use ccm::aead::{generic_array::GenericArray, Aead, Payload};
+
+use crate::{
+    Aes128Ccm13, BatteryPercent, ButtonPress, DecodeError, DeviceKey, Event, Measurement,
+    RelativeHumidity, Temperature, Voltage,
+};
+
+/// 0xFCD2 Allterco Robotics (BTHome)
+pub const BTHOME_SERVICE_UUID: u16 = 0xFCD2;
+
+const DEVICE_INFO_ENCRYPTED: u8 = 1 << 0;
+const DEVICE_INFO_TRIGGER_BASED: u8 = 1 << 2;
+
+const COUNTER_LEN: usize = 4;
+const MIC_LEN: usize = 4;
+
+const OBJECT_PACKET_ID: u8 = 0x00;
+const OBJECT_ILLUMINANCE: u8 = 0x05;
+const OBJECT_MOTION: u8 = 0x21;
+const OBJECT_BUTTON: u8 = 0x3A;
+const OBJECT_TEXT: u8 = 0x53;
+const OBJECT_RAW: u8 = 0x54;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Unsigned,
+    Signed,
+    Binary,
+    Button,
+    Dimmer,
+}
+
+/// Entry of the BTHome object table
+struct ObjectSpec {
+    id: u8,
+    name: &'static str,
+    len: usize,
+    kind: Kind,
+    factor: f64,
+    unit: &'static str,
+}
+
+const fn spec(
+    id: u8,
+    name: &'static str,
+    len: usize,
+    kind: Kind,
+    factor: f64,
+    unit: &'static str,
+) -> ObjectSpec {
+    ObjectSpec {
+        id,
+        name,
+        len,
+        kind,
+        factor,
+        unit,
+    }
+}
+
+use Kind::*;
+
+#[rustfmt::skip]
+const OBJECTS: &[ObjectSpec] = &[
+    spec(0x00, "packet_id", 1, Unsigned, 1.0, ""),
+    spec(0x01, "battery", 1, Unsigned, 1.0, "%"),
+    spec(0x02, "temperature", 2, Signed, 0.01, "°C"),
+    spec(0x03, "humidity", 2, Unsigned, 0.01, "%"),
+    spec(0x04, "pressure", 3, Unsigned, 0.01, "hPa"),
+    spec(0x05, "illuminance", 3, Unsigned, 0.01, "lux"),
+    spec(0x06, "mass", 2, Unsigned, 0.01, "kg"),
+    spec(0x07, "mass", 2, Unsigned, 0.01, "lb"),
+    spec(0x08, "dewpoint", 2, Signed, 0.01, "°C"),
+    spec(0x09, "count", 1, Unsigned, 1.0, ""),
+    spec(0x0A, "energy", 3, Unsigned, 0.001, "kWh"),
+    spec(0x0B, "power", 3, Unsigned, 0.01, "W"),
+    spec(0x0C, "voltage", 2, Unsigned, 0.001, "V"),
+    spec(0x0D, "pm2_5", 2, Unsigned, 1.0, "µg/m³"),
+    spec(0x0E, "pm10", 2, Unsigned, 1.0, "µg/m³"),
+    spec(0x0F, "generic_boolean", 1, Binary, 1.0, ""),
+    spec(0x10, "power", 1, Binary, 1.0, ""),
+    spec(0x11, "opening", 1, Binary, 1.0, ""),
+    spec(0x12, "co2", 2, Unsigned, 1.0, "ppm"),
+    spec(0x13, "tvoc", 2, Unsigned, 1.0, "µg/m³"),
+    spec(0x14, "moisture", 2, Unsigned, 0.01, "%"),
+    spec(0x15, "battery_low", 1, Binary, 1.0, ""),
+    spec(0x16, "battery_charging", 1, Binary, 1.0, ""),
+    spec(0x17, "carbon_monoxide", 1, Binary, 1.0, ""),
+    spec(0x18, "cold", 1, Binary, 1.0, ""),
+    spec(0x19, "connectivity", 1, Binary, 1.0, ""),
+    spec(0x1A, "door", 1, Binary, 1.0, ""),
+    spec(0x1B, "garage_door", 1, Binary, 1.0, ""),
+    spec(0x1C, "gas", 1, Binary, 1.0, ""),
+    spec(0x1D, "heat", 1, Binary, 1.0, ""),
+    spec(0x1E, "light", 1, Binary, 1.0, ""),
+    spec(0x1F, "lock", 1, Binary, 1.0, ""),
+    spec(0x20, "moisture", 1, Binary, 1.0, ""),
+    spec(0x21, "motion", 1, Binary, 1.0, ""),
+    spec(0x22, "moving", 1, Binary, 1.0, ""),
+    spec(0x23, "occupancy", 1, Binary, 1.0, ""),
+    spec(0x24, "plug", 1, Binary, 1.0, ""),
+    spec(0x25, "presence", 1, Binary, 1.0, ""),
+    spec(0x26, "problem", 1, Binary, 1.0, ""),
+    spec(0x27, "running", 1, Binary, 1.0, ""),
+    spec(0x28, "safety", 1, Binary, 1.0, ""),
+    spec(0x29, "smoke", 1, Binary, 1.0, ""),
+    spec(0x2A, "sound", 1, Binary, 1.0, ""),
+    spec(0x2B, "tamper", 1, Binary, 1.0, ""),
+    spec(0x2C, "vibration", 1, Binary, 1.0, ""),
+    spec(0x2D, "window", 1, Binary, 1.0, ""),
+    spec(0x2E, "humidity", 1, Unsigned, 1.0, "%"),
+    spec(0x2F, "moisture", 1, Unsigned, 1.0, "%"),
+    spec(0x3A, "button", 1, Button, 1.0, ""),
+    spec(0x3C, "dimmer", 2, Dimmer, 1.0, ""),
+    spec(0x3D, "count", 2, Unsigned, 1.0, ""),
+    spec(0x3E, "count", 4, Unsigned, 1.0, ""),
+    spec(0x3F, "rotation", 2, Signed, 0.1, "°"),
+    spec(0x40, "distance", 2, Unsigned, 1.0, "mm"),
+    spec(0x41, "distance", 2, Unsigned, 0.1, "m"),
+    spec(0x42, "duration", 3, Unsigned, 0.001, "s"),
+    spec(0x43, "current", 2, Unsigned, 0.001, "A"),
+    spec(0x44, "speed", 2, Unsigned, 0.01, "m/s"),
+    spec(0x45, "temperature", 2, Signed, 0.1, "°C"),
+    spec(0x46, "uv_index", 1, Unsigned, 0.1, ""),
+    spec(0x47, "volume", 2, Unsigned, 0.1, "L"),
+    spec(0x48, "volume", 2, Unsigned, 1.0, "mL"),
+    spec(0x49, "volume_flow_rate", 2, Unsigned, 0.001, "m³/h"),
+    spec(0x4A, "voltage", 2, Unsigned, 0.1, "V"),
+    spec(0x4B, "gas", 3, Unsigned, 0.001, "m³"),
+    spec(0x4C, "gas", 4, Unsigned, 0.001, "m³"),
+    spec(0x4D, "energy", 4, Unsigned, 0.001, "kWh"),
+    spec(0x4E, "volume", 4, Unsigned, 0.001, "L"),
+    spec(0x4F, "water", 4, Unsigned, 0.001, "L"),
+    spec(0x50, "timestamp", 4, Unsigned, 1.0, "s"),
+    spec(0x51, "acceleration", 2, Unsigned, 0.001, "m/s²"),
+    spec(0x52, "gyroscope", 2, Unsigned, 0.001, "°/s"),
+    spec(0x55, "volume_storage", 4, Unsigned, 0.001, "L"),
+    spec(0x56, "conductivity", 2, Unsigned, 1.0, "µS/cm"),
+    spec(0x57, "temperature", 1, Signed, 1.0, "°C"),
+    spec(0x58, "temperature", 1, Signed, 0.35, "°C"),
+    spec(0x59, "count", 1, Signed, 1.0, ""),
+    spec(0x5A, "count", 2, Signed, 1.0, ""),
+    spec(0x5B, "count", 4, Signed, 1.0, ""),
+    spec(0x5C, "power", 4, Signed, 0.01, "W"),
+    spec(0x5D, "current", 2, Signed, 0.001, "A"),
+    spec(0x5E, "direction", 2, Unsigned, 0.01, "°"),
+    spec(0x5F, "precipitation", 2, Unsigned, 0.1, "mm"),
+    spec(0x60, "channel", 1, Unsigned, 1.0, ""),
+    spec(0xF0, "device_type_id", 2, Unsigned, 1.0, ""),
+    spec(0xF1, "firmware_version", 4, Unsigned, 1.0, ""),
+    spec(0xF2, "firmware_version", 3, Unsigned, 1.0, ""),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BtHomeValue {
+    /// Value scaled to `BtHomeObject::unit`
+    Number(f64),
+    Binary(bool),
+    /// 0 none, 1 press, 2 double press, 3 triple press, 4 long press, ...
+    Button(u8),
+    Dimmer {
+        event: u8,
+        steps: u8,
+    },
+    Text(String),
+    Raw(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BtHomeObject {
+    pub id: u8,
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub value: BtHomeValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BtHomeFrame {
+    pub device_info: u8,
+    /// Encrypted frames counter
+    pub counter: Option<u32>,
+    pub objects: Vec<BtHomeObject>,
+}
+
+impl BtHomeFrame {
+    pub fn is_encrypted(&self) -> bool {
+        self.device_info & DEVICE_INFO_ENCRYPTED != 0
+    }
+
+    pub fn is_trigger_based(&self) -> bool {
+        self.device_info & DEVICE_INFO_TRIGGER_BASED != 0
+    }
+
+    pub fn version(&self) -> u8 {
+        self.device_info >> 5
+    }
+
+    pub fn packet_id(&self) -> Option<u8> {
+        self.objects.iter().find_map(|o| match o.value {
+            BtHomeValue::Number(n) if o.id == OBJECT_PACKET_ID => Some(n as u8),
+            _ => None,
+        })
+    }
+
+    /// The objects having a `Measurement` counterpart
+    ///
+    /// Motion comes with the illuminance object of the frame, 0 without one. Buttons are indexed
+    /// by their order in the frame, as multi-button devices send one object per button.
+    pub fn measurements(&self) -> Vec<Measurement> {
+        let illuminance = self.objects.iter().find_map(|o| match o.value {
+            BtHomeValue::Number(n) if o.id == OBJECT_ILLUMINANCE => Some(n.round() as u32),
+            _ => None,
+        });
+        let mut buttons = 0..;
+
+        self.objects
+            .iter()
+            .filter_map(|o| match (o.id, &o.value) {
+                (0x01, BtHomeValue::Number(n)) => {
+                    Some(Measurement::Battery(BatteryPercent::new(*n as u8)))
+                }
+                (0x02 | 0x45 | 0x57 | 0x58, BtHomeValue::Number(n)) => {
+                    Some(Measurement::Temperature(Temperature::from_decidegrees(
+                        (n * 10.0).round() as i16,
+                    )))
+                }
+                (0x03 | 0x2E, BtHomeValue::Number(n)) => Some(Measurement::Humidity(
+                    RelativeHumidity::from_decipercent((n * 10.0).round() as u16),
+                )),
+                (0x04, BtHomeValue::Number(n)) => {
+                    Some(Measurement::Pressure((n * 100.0).round() as u32))
+                }
+                (OBJECT_ILLUMINANCE, BtHomeValue::Number(n)) => {
+                    Some(Measurement::Illuminance(n.round() as u32))
+                }
+                (0x0C, BtHomeValue::Number(n)) => Some(Measurement::Voltage(
+                    Voltage::from_millivolts((n * 1000.0).round() as u16),
+                )),
+                (0x0D, BtHomeValue::Number(n)) => Some(Measurement::Pm25(*n as u16)),
+                (0x0E, BtHomeValue::Number(n)) => Some(Measurement::Pm10(*n as u16)),
+                (0x12, BtHomeValue::Number(n)) => Some(Measurement::Co2(*n as u16)),
+                (0x14 | 0x2F, BtHomeValue::Number(n)) => {
+                    Some(Measurement::Moisture(n.round() as u8))
+                }
+                (0x56, BtHomeValue::Number(n)) => Some(Measurement::Conductivity(*n as u16)),
+                (OBJECT_MOTION, BtHomeValue::Binary(true)) => {
+                    Some(Measurement::Event(Event::Motion {
+                        illuminance: illuminance.unwrap_or(0),
+                    }))
+                }
+                (OBJECT_MOTION, BtHomeValue::Binary(false)) => {
+                    Some(Measurement::Event(Event::NoMotion { seconds: 0 }))
+                }
+                (OBJECT_BUTTON, BtHomeValue::Button(event)) => {
+                    let index = buttons.next().unwrap_or(u16::MAX);
+                    let press = match event {
+                        1 => ButtonPress::Single,
+                        2 => ButtonPress::Double,
+                        3 => ButtonPress::Triple,
+                        4 => ButtonPress::Long,
+                        // none, and the long double / triple and hold presses
+                        _ => return None,
+                    };
+                    Some(Measurement::Event(Event::Button { index, press }))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Parse plain BTHome v2 service data
+pub fn parse_bthome(data: &[u8]) -> Result<BtHomeFrame, DecodeError> {
+    let (&device_info, objects) = data.split_first().ok_or(DecodeError::FrameTooShort)?;
+    check_version(device_info)?;
+
+    if device_info & DEVICE_INFO_ENCRYPTED != 0 {
+        return Err(DecodeError::Encrypted);
+    }
+
+    Ok(BtHomeFrame {
+        device_info,
+        counter: None,
+        objects: parse_objects(objects)?,
+    })
+}
+
+/// Decrypt and parse encrypted BTHome v2 service data, `mac` being the advertiser address
+pub fn decrypt_bthome(data: &[u8], mac: [u8; 6], key: &[u8]) -> Result<BtHomeFrame, DecodeError> {
+    decrypt_bthome_with(data, mac, &DeviceKey::new(key)?)
+}
+
+/// Same as `decrypt_bthome` with an already parsed key
+pub fn decrypt_bthome_with(
+    data: &[u8],
+    mac: [u8; 6],
+    key: &DeviceKey,
+) -> Result<BtHomeFrame, DecodeError> {
+    let (&device_info, rest) = data.split_first().ok_or(DecodeError::FrameTooShort)?;
+    check_version(device_info)?;
+
+    if device_info & DEVICE_INFO_ENCRYPTED == 0 {
+        return Err(DecodeError::Unencrypted);
+    }
+    if rest.len() < COUNTER_LEN + MIC_LEN {
+        return Err(DecodeError::FrameTooShort);
+    }
+
+    let (encrypted, trailer) = rest.split_at(rest.len() - COUNTER_LEN - MIC_LEN);
+    let (counter, mic) = trailer.split_at(COUNTER_LEN);
+
+    let nonce = [
+        &mac[..],
+        &BTHOME_SERVICE_UUID.to_le_bytes(),
+        &[device_info],
+        counter,
+    ]
+    .concat();
+
+    let cipher: Aes128Ccm13 = key.ccm()?;
+    let plain = cipher
+        .decrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: &[encrypted, mic].concat(),
+                aad: &[],
+            },
+        )
+        .map_err(|_| DecodeError::TagMismatch)?;
+
+    Ok(BtHomeFrame {
+        device_info,
+        counter: Some(u32::from_le_bytes([
+            counter[0], counter[1], counter[2], counter[3],
+        ])),
+        objects: parse_objects(&plain)?,
+    })
+}
+
+fn check_version(device_info: u8) -> Result<(), DecodeError> {
+    if device_info >> 5 != 2 {
+        return Err(DecodeError::UnknownFormat);
+    }
+    Ok(())
+}
+
+fn parse_objects(mut data: &[u8]) -> Result<Vec<BtHomeObject>, DecodeError> {
+    let mut objects = Vec::new();
+
+    while let [id, rest @ ..] = data {
+        let id = *id;
+
+        if id == OBJECT_TEXT || id == OBJECT_RAW {
+            let (&len, rest) = rest.split_first().ok_or(DecodeError::FrameTooShort)?;
+            let value = rest.get(..len as usize).ok_or(DecodeError::FrameTooShort)?;
+            objects.push(BtHomeObject {
+                id,
+                name: if id == OBJECT_TEXT { "text" } else { "raw" },
+                unit: "",
+                value: if id == OBJECT_TEXT {
+                    BtHomeValue::Text(String::from_utf8_lossy(value).into_owned())
+                } else {
+                    BtHomeValue::Raw(value.to_vec())
+                },
+            });
+            data = &rest[len as usize..];
+            continue;
+        }
+
+        // Objects are sized by the table, an unknown id prevents reading the following ones
+        let spec = OBJECTS
+            .iter()
+            .find(|spec| spec.id == id)
+            .ok_or(DecodeError::UnsupportedObject(id as u16))?;
+        let value = rest.get(..spec.len).ok_or(DecodeError::FrameTooShort)?;
+
+        let value = match spec.kind {
+            Unsigned => BtHomeValue::Number(unsigned(value) as f64 * spec.factor),
+            Signed => BtHomeValue::Number(signed(value) as f64 * spec.factor),
+            Binary => BtHomeValue::Binary(value[0] != 0),
+            Button => BtHomeValue::Button(value[0]),
+            Dimmer => BtHomeValue::Dimmer {
+                event: value[0],
+                steps: value[1],
+            },
+        };
+
+        objects.push(BtHomeObject {
+            id,
+            name: spec.name,
+            unit: spec.unit,
+            value,
+        });
+        data = &rest[spec.len..];
+    }
+
+    Ok(objects)
+}
+
+fn unsigned(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, byte| (acc << 8) | *byte as u64)
+}
+
+fn signed(bytes: &[u8]) -> i64 {
+    let shift = 64 - 8 * bytes.len() as u32;
+    ((unsigned(bytes) << shift) as i64) >> shift
+}
+
+#[cfg(test)]
+pub(crate) fn encrypt_bthome(plain: &[u8], counter: u32, mac: [u8; 6], key: &[u8]) -> Vec<u8> {
+    use ccm::aead::KeyInit;
+
+    let device_info = 0x41;
+    let counter = counter.to_le_bytes();
+    let nonce = [
+        &mac[..],
+        &BTHOME_SERVICE_UUID.to_le_bytes(),
+        &[device_info],
+        &counter,
+    ]
+    .concat();
+    let encrypted = Aes128Ccm13::new_from_slice(key)
+        .unwrap()
+        .encrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: plain,
+                aad: &[],
+            },
+        )
+        .unwrap();
+    let (encrypted, mic) = encrypted.split_at(plain.len());
+
+    [&[device_info], encrypted, &counter, mic].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn plain() {
+        // 25.06°C 50.55%
+        let frame = parse_bthome(&decode_hex("4002CA0903BF13").unwrap()).unwrap();
+
+        assert!(!frame.is_encrypted());
+        assert_eq!(frame.version(), 2);
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::Temperature(Temperature::from_decidegrees(251)),
+                Measurement::Humidity(RelativeHumidity::from_decipercent(506)),
+            ]
+        );
+    }
+
+    #[test]
+    fn objects() {
+        // packet id, battery, pressure 1008.83 hPa, motion, button double press, text, temperature -2.5°C
+        let frame =
+            parse_bthome(&decode_hex("440009016104138A0121013A02530348656C45E7FF").unwrap())
+                .unwrap();
+
+        assert_eq!(frame.packet_id(), Some(9));
+        assert_eq!(frame.objects[3].name, "motion");
+        assert_eq!(frame.objects[3].value, BtHomeValue::Binary(true));
+        assert_eq!(frame.objects[4].value, BtHomeValue::Button(2));
+        assert_eq!(frame.objects[5].value, BtHomeValue::Text("Hel".to_string()));
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::Battery(BatteryPercent::new(97)),
+                Measurement::Pressure(100883),
+                Measurement::Event(Event::Motion { illuminance: 0 }),
+                Measurement::Event(Event::Button {
+                    index: 0,
+                    press: ButtonPress::Double,
+                }),
+                Measurement::Temperature(Temperature::from_decidegrees(-25)),
+            ]
+        );
+
+        assert_eq!(
+            parse_bthome(&decode_hex("40FE01").unwrap()),
+            Err(DecodeError::UnsupportedObject(0xFE))
+        );
+        assert_eq!(
+            parse_bthome(&decode_hex("4002CA").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_bthome(&decode_hex("2002CA09").unwrap()),
+            Err(DecodeError::UnknownFormat)
+        );
+    }
+
+    #[test]
+    fn events() {
+        // illuminance 110 lux, motion, first button without event, second button long press
+        let frame = parse_bthome(&decode_hex("4005F82A0021013A003A04").unwrap()).unwrap();
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::Illuminance(110),
+                Measurement::Event(Event::Motion { illuminance: 110 }),
+                Measurement::Event(Event::Button {
+                    index: 1,
+                    press: ButtonPress::Long,
+                }),
+            ]
+        );
+
+        let frame = parse_bthome(&decode_hex("402100").unwrap()).unwrap();
+        assert_eq!(
+            frame.measurements(),
+            vec![Measurement::Event(Event::NoMotion { seconds: 0 })]
+        );
+    }
+
+    #[test]
+    fn encrypted() {
+        // example of the BTHome v2 specification: 25.06°C 50.55%, counter 00112233
+        let key = decode_hex("231d39c1d7cc1ab1aee224cd096db932").unwrap();
+        let mac = [0x54, 0x48, 0xE6, 0x8F, 0x80, 0xA5];
+        let data = decode_hex("41A47266C95F730011223378237214").unwrap();
+        assert_eq!(parse_bthome(&data), Err(DecodeError::Encrypted));
+
+        let frame = decrypt_bthome(&data, mac, &key).unwrap();
+        assert!(frame.is_encrypted());
+        assert_eq!(frame.counter, Some(0x33221100));
+        let values: Vec<f64> = frame
+            .objects
+            .iter()
+            .map(|o| match o.value {
+                BtHomeValue::Number(n) => n,
+                _ => panic!("{:?}", o),
+            })
+            .collect();
+        assert_eq!(values.len(), 2);
+        assert!((values[0] - 25.06).abs() < 1e-9);
+        assert!((values[1] - 50.55).abs() < 1e-9);
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::Temperature(Temperature::from_decidegrees(251)),
+                Measurement::Humidity(RelativeHumidity::from_decipercent(506)),
+            ]
+        );
+
+        // the test encryption gives the same frame
+        assert_eq!(
+            encrypt_bthome(&decode_hex("02CA0903BF13").unwrap(), 0x33221100, mac, &key),
+            data
+        );
+        assert_eq!(
+            decrypt_bthome(&data, [0; 6], &key),
+            Err(DecodeError::TagMismatch)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(objects in vec(any::<u8>(), 0..40), mac in any::<[u8; 6]>()) {
+            // version 2, plain and encrypted
+            for device_info in [0x40, 0x41] {
+                let data = [&[device_info][..], &objects].concat();
+                let _ = parse_bthome(&data);
+                let _ = decrypt_bthome(&data, mac, &[0; 16]);
+            }
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Advertisements recorded in btsnoop HCI logs (btmon, Android) and pcap files (Wireshark)
+//!
+//! Supported encapsulations:
+//!
+//! ```text
+//! btsnoop  1001 HCI UART (H4) | 1002 HCI un-encapsulated | 2001 Linux monitor (btmon)
+//! pcap      187 HCI H4 | 201 HCI H4 with direction header | 256 LE link layer with PHDR
+//! ```
+//!
+//! HCI captures carry LE (extended) advertising report events, link layer captures the
+//! advertising channel PDUs themselves. A truncated last record, as left by an interrupted
+//! capture, is ignored.
+
+use std::{fmt, time::Duration};
+
+/// An advertisement pulled out of a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advertisement {
+    /// Capture time since the Unix epoch
+    pub timestamp: Duration,
+    /// Advertiser address in display order
+    pub mac: [u8; 6],
+    /// dBm, when known
+    pub rssi: Option<i8>,
+    /// Advertising data (AD structures)
+    pub data: Vec<u8>,
+}
+
+/// Why a capture could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// Neither a btsnoop nor a pcap file
+    UnknownFormat,
+    /// File header shorter than expected
+    Truncated,
+    /// btsnoop datalink or pcap link type not handled
+    UnsupportedLinkType(u32),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::UnknownFormat => write!(f, "not a btsnoop or pcap capture"),
+            CaptureError::Truncated => write!(f, "truncated capture header"),
+            CaptureError::UnsupportedLinkType(link_type) => {
+                write!(f, "unsupported link type {}", link_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+const BTSNOOP_MAGIC: &[u8; 8] = b"btsnoop\0";
+const BTSNOOP_HCI_UART: u32 = 1001;
+const BTSNOOP_HCI_UNENCAPSULATED: u32 = 1002;
+const BTSNOOP_MONITOR: u32 = 2001;
+/// Microseconds between 0000-01-01 (btsnoop epoch) and the Unix epoch, as used by Wireshark
+const BTSNOOP_UNIX_OFFSET_US: u64 = 0x00DC_DDB3_0F2F_8000;
+/// Un-encapsulated packet flags: bit 0 set for received packets, bit 1 for commands and events,
+/// so events (received) are flagged 3 and commands (sent) 2
+const BTSNOOP_FLAG_RECEIVED: u32 = 1 << 0;
+const BTSNOOP_FLAG_COMMAND_EVENT: u32 = 1 << 1;
+const BTSNOOP_EVENT: u32 = BTSNOOP_FLAG_RECEIVED | BTSNOOP_FLAG_COMMAND_EVENT;
+/// Monitor opcode of HCI event packets, in the low 16 bits of the flags
+const MONITOR_EVENT_PACKET: u32 = 3;
+
+const PCAP_MAGIC_US: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NS: u32 = 0xA1B2_3C4D;
+const LINKTYPE_BLUETOOTH_HCI_H4: u32 = 187;
+const LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR: u32 = 201;
+const LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR: u32 = 256;
+
+const H4_EVENT: u8 = 0x04;
+const HCI_LE_META_EVENT: u8 = 0x3E;
+const LE_ADVERTISING_REPORT: u8 = 0x02;
+const LE_EXTENDED_ADVERTISING_REPORT: u8 = 0x0D;
+/// Extended reports use 127 when the RSSI is not available
+const RSSI_UNAVAILABLE: i8 = 127;
+
+const ADVERTISING_ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+const LE_LL_PHDR_LEN: usize = 10;
+const LE_LL_SIGNAL_POWER_VALID: u16 = 1 << 1;
+
+/// Whether `data` starts like a btsnoop or pcap file
+pub fn is_capture(data: &[u8]) -> bool {
+    data.starts_with(BTSNOOP_MAGIC) || pcap_format(data).is_some()
+}
+
+/// Read a btsnoop or pcap capture, told apart by their magic number
+pub fn read_capture(data: &[u8]) -> Result<Vec<Advertisement>, CaptureError> {
+    if data.starts_with(BTSNOOP_MAGIC) {
+        read_btsnoop(data)
+    } else {
+        read_pcap(data)
+    }
+}
+
+/// btsnoop: big-endian header and records, 64 bits timestamps in µs since year 0
+pub fn read_btsnoop(data: &[u8]) -> Result<Vec<Advertisement>, CaptureError> {
+    if !data.starts_with(BTSNOOP_MAGIC) {
+        return Err(CaptureError::UnknownFormat);
+    }
+    let header = data.get(..16).ok_or(CaptureError::Truncated)?;
+    let datalink = u32::from_be_bytes([header[12], header[13], header[14], header[15]]);
+    if ![
+        BTSNOOP_HCI_UART,
+        BTSNOOP_HCI_UNENCAPSULATED,
+        BTSNOOP_MONITOR,
+    ]
+    .contains(&datalink)
+    {
+        return Err(CaptureError::UnsupportedLinkType(datalink));
+    }
+
+    let mut advertisements = Vec::new();
+    let mut rest = &data[16..];
+
+    // original len | included len | flags | drops | timestamp | packet
+    while rest.len() >= 24 {
+        let included_len = be_u32(&rest[4..8]) as usize;
+        let flags = be_u32(&rest[8..12]);
+        let timestamp = u64::from_be_bytes([
+            rest[16], rest[17], rest[18], rest[19], rest[20], rest[21], rest[22], rest[23],
+        ]);
+        let Some(packet) = rest[24..].get(..included_len) else {
+            break;
+        };
+        rest = &rest[24 + included_len..];
+
+        let timestamp = Duration::from_micros(timestamp.saturating_sub(BTSNOOP_UNIX_OFFSET_US));
+        let event = match datalink {
+            BTSNOOP_HCI_UART => h4_event(packet),
+            BTSNOOP_HCI_UNENCAPSULATED if flags & BTSNOOP_EVENT == BTSNOOP_EVENT => Some(packet),
+            BTSNOOP_MONITOR if flags & 0xFFFF == MONITOR_EVENT_PACKET => Some(packet),
+            _ => None,
+        };
+        if let Some(event) = event {
+            hci_event(event, timestamp, &mut advertisements);
+        }
+    }
+
+    Ok(advertisements)
+}
+
+/// pcap: header endianness and timestamp resolution given by the magic number
+pub fn read_pcap(data: &[u8]) -> Result<Vec<Advertisement>, CaptureError> {
+    let (big_endian, nanoseconds) = pcap_format(data).ok_or(CaptureError::UnknownFormat)?;
+    let header = data.get(..24).ok_or(CaptureError::Truncated)?;
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+
+    let link_type = read_u32(&header[20..24]);
+    if ![
+        LINKTYPE_BLUETOOTH_HCI_H4,
+        LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR,
+        LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR,
+    ]
+    .contains(&link_type)
+    {
+        return Err(CaptureError::UnsupportedLinkType(link_type));
+    }
+
+    let mut advertisements = Vec::new();
+    let mut rest = &data[24..];
+
+    // seconds | sub-seconds | included len | original len | packet
+    while rest.len() >= 16 {
+        let seconds = read_u32(&rest[0..4]) as u64;
+        let fraction = read_u32(&rest[4..8]);
+        let included_len = read_u32(&rest[8..12]) as usize;
+        let Some(packet) = rest[16..].get(..included_len) else {
+            break;
+        };
+        rest = &rest[16 + included_len..];
+
+        let timestamp = if nanoseconds {
+            Duration::new(seconds, fraction)
+        } else {
+            Duration::from_secs(seconds) + Duration::from_micros(fraction as u64)
+        };
+
+        match link_type {
+            LINKTYPE_BLUETOOTH_HCI_H4 => {
+                if let Some(event) = h4_event(packet) {
+                    hci_event(event, timestamp, &mut advertisements);
+                }
+            }
+            LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR => {
+                // 4 bytes direction header
+                if let Some(event) = packet.get(4..).and_then(h4_event) {
+                    hci_event(event, timestamp, &mut advertisements);
+                }
+            }
+            _ => {
+                if let Some(advertisement) = le_ll_packet(packet, timestamp) {
+                    advertisements.push(advertisement);
+                }
+            }
+        }
+    }
+
+    Ok(advertisements)
+}
+
+/// Endianness (big endian) and timestamp resolution (nanoseconds) of a pcap file
+fn pcap_format(data: &[u8]) -> Option<(bool, bool)> {
+    let magic = [*data.first()?, *data.get(1)?, *data.get(2)?, *data.get(3)?];
+
+    match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+        (PCAP_MAGIC_US, _) => Some((false, false)),
+        (PCAP_MAGIC_NS, _) => Some((false, true)),
+        (_, PCAP_MAGIC_US) => Some((true, false)),
+        (_, PCAP_MAGIC_NS) => Some((true, true)),
+        _ => None,
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn reversed_mac(address: &[u8]) -> [u8; 6] {
+    [
+        address[5], address[4], address[3], address[2], address[1], address[0],
+    ]
+}
+
+/// HCI event of a H4 packet, `None` for other packet types
+fn h4_event(packet: &[u8]) -> Option<&[u8]> {
+    match packet.split_first() {
+        Some((&H4_EVENT, event)) => Some(event),
+        _ => None,
+    }
+}
+
+/// Push the advertising reports of an HCI event: code | length | parameters
+fn hci_event(event: &[u8], timestamp: Duration, advertisements: &mut Vec<Advertisement>) {
+    let [HCI_LE_META_EVENT, _len, subevent, count, reports @ ..] = event else {
+        return;
+    };
+
+    let mut reports = reports;
+    for _ in 0..*count {
+        let parsed = match *subevent {
+            LE_ADVERTISING_REPORT => advertising_report(reports),
+            LE_EXTENDED_ADVERTISING_REPORT => extended_advertising_report(reports),
+            _ => return,
+        };
+        let Some((mac, rssi, data, next)) = parsed else {
+            return;
+        };
+
+        advertisements.push(Advertisement {
+            timestamp,
+            mac,
+            rssi,
+            data: data.to_vec(),
+        });
+        reports = next;
+    }
+}
+
+type Report<'a> = ([u8; 6], Option<i8>, &'a [u8], &'a [u8]);
+
+/// event type | address type | address | data len | data | RSSI
+fn advertising_report(report: &[u8]) -> Option<Report<'_>> {
+    let address = report.get(2..8)?;
+    let len = *report.get(8)? as usize;
+    let data = report.get(9..9 + len)?;
+    let rssi = *report.get(9 + len)? as i8;
+
+    Some((reversed_mac(address), Some(rssi), data, &report[10 + len..]))
+}
+
+/// event type (2) | address type | address | PHYs (2) | SID | TX power | RSSI | interval (2)
+/// | direct address type | direct address | data len | data
+fn extended_advertising_report(report: &[u8]) -> Option<Report<'_>> {
+    let address = report.get(3..9)?;
+    let rssi = *report.get(13)? as i8;
+    let len = *report.get(23)? as usize;
+    let data = report.get(24..24 + len)?;
+
+    let rssi = (rssi != RSSI_UNAVAILABLE).then_some(rssi);
+    Some((reversed_mac(address), rssi, data, &report[24 + len..]))
+}
+
+/// PHDR | access address | PDU header | AdvA | AdvData | CRC
+fn le_ll_packet(packet: &[u8], timestamp: Duration) -> Option<Advertisement> {
+    let phdr = packet.get(..LE_LL_PHDR_LEN)?;
+    let flags = u16::from_le_bytes([phdr[8], phdr[9]]);
+    let rssi = (flags & LE_LL_SIGNAL_POWER_VALID != 0).then_some(phdr[1] as i8);
+
+    let pdu = &packet[LE_LL_PHDR_LEN..];
+    let access_address = u32::from_le_bytes(pdu.get(..4)?.try_into().ok()?);
+    if access_address != ADVERTISING_ACCESS_ADDRESS {
+        return None;
+    }
+
+    // ADV_IND, ADV_NONCONN_IND, SCAN_RSP, ADV_SCAN_IND carry AdvA and AdvData
+    let pdu_type = pdu.get(4)? & 0x0F;
+    if ![0x00, 0x02, 0x04, 0x06].contains(&pdu_type) {
+        return None;
+    }
+    let len = *pdu.get(5)? as usize;
+    let payload = pdu.get(6..6 + len)?;
+    let address = payload.get(..6)?;
+
+    Some(Advertisement {
+        timestamp,
+        mac: reversed_mac(address),
+        rssi,
+        data: payload[6..].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+    const ADVERTISEMENT: &str = "0201061A1695FE58585B054F5C2D4E38C1A44886C7D7A10000007A54168F";
+
+    /// LE advertising report event for `ADVERTISEMENT`, RSSI -70 dBm
+    fn report_event() -> Vec<u8> {
+        let data = decode_hex(ADVERTISEMENT).unwrap();
+        let mut report = vec![0x00, 0x00, 0x5C, 0x2D, 0x4E, 0x38, 0xC1, 0xA4];
+        report.push(data.len() as u8);
+        report.extend_from_slice(&data);
+        report.push(-70i8 as u8);
+
+        let mut event = vec![
+            HCI_LE_META_EVENT,
+            report.len() as u8 + 2,
+            LE_ADVERTISING_REPORT,
+            1,
+        ];
+        event.extend_from_slice(&report);
+        event
+    }
+
+    fn expected(timestamp: Duration, rssi: Option<i8>) -> Advertisement {
+        Advertisement {
+            timestamp,
+            mac: MAC,
+            rssi,
+            data: decode_hex(ADVERTISEMENT).unwrap(),
+        }
+    }
+
+    fn btsnoop(datalink: u32, records: &[(u32, Vec<u8>)], timestamp: u64) -> Vec<u8> {
+        let mut capture = BTSNOOP_MAGIC.to_vec();
+        capture.extend_from_slice(&1u32.to_be_bytes());
+        capture.extend_from_slice(&datalink.to_be_bytes());
+        for (flags, packet) in records {
+            capture.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+            capture.extend_from_slice(&flags.to_be_bytes());
+            capture.extend_from_slice(&0u32.to_be_bytes());
+            capture.extend_from_slice(&(timestamp + BTSNOOP_UNIX_OFFSET_US).to_be_bytes());
+            capture.extend_from_slice(packet);
+        }
+        capture
+    }
+
+    fn pcap(link_type: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut capture = PCAP_MAGIC_US.to_le_bytes().to_vec();
+        capture.extend_from_slice(&[2, 0, 4, 0]);
+        capture.extend_from_slice(&[0; 8]);
+        capture.extend_from_slice(&65535u32.to_le_bytes());
+        capture.extend_from_slice(&link_type.to_le_bytes());
+        for packet in packets {
+            capture.extend_from_slice(&1_700_000_000u32.to_le_bytes());
+            capture.extend_from_slice(&250_000u32.to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(packet);
+        }
+        capture
+    }
+
+    #[test]
+    fn btsnoop_logs() {
+        let timestamp = 1_700_000_000_250_000;
+        let time = Duration::from_micros(timestamp);
+        let h4 = [&[H4_EVENT][..], &report_event()].concat();
+        // HCI command, skipped
+        let command = vec![0x01, 0x0C, 0x20, 0x02, 0x01, 0x00];
+
+        let capture = btsnoop(BTSNOOP_HCI_UART, &[(0, command), (3, h4)], timestamp);
+        assert_eq!(read_capture(&capture), Ok(vec![expected(time, Some(-70))]));
+
+        // btmon: opcode 3 for events
+        let capture = btsnoop(BTSNOOP_MONITOR, &[(3, report_event())], timestamp);
+        assert_eq!(read_capture(&capture), Ok(vec![expected(time, Some(-70))]));
+
+        let mut truncated = btsnoop(
+            BTSNOOP_HCI_UNENCAPSULATED,
+            &[(3, report_event())],
+            timestamp,
+        );
+        assert_eq!(read_btsnoop(&truncated).unwrap().len(), 1);
+        truncated.truncate(truncated.len() - 1);
+        assert_eq!(read_btsnoop(&truncated), Ok(vec![]));
+
+        // sent command (flags 2) with the bytes of an event, not parsed as one
+        let sent = btsnoop(
+            BTSNOOP_HCI_UNENCAPSULATED,
+            &[(2, report_event()), (3, report_event())],
+            timestamp,
+        );
+        assert_eq!(read_btsnoop(&sent), Ok(vec![expected(time, Some(-70))]));
+
+        assert_eq!(
+            read_capture(&btsnoop(1003, &[], 0)),
+            Err(CaptureError::UnsupportedLinkType(1003))
+        );
+        assert_eq!(read_capture(b"btsnoop\0"), Err(CaptureError::Truncated));
+        assert_eq!(read_capture(b"nothing"), Err(CaptureError::UnknownFormat));
+        assert!(is_capture(b"btsnoop\0"));
+        assert!(is_capture(&PCAP_MAGIC_NS.to_be_bytes()));
+        assert!(!is_capture(b"0201061A"));
+    }
+
+    #[test]
+    fn extended_reports() {
+        let data = decode_hex(ADVERTISEMENT).unwrap();
+        let mut report = vec![0x13, 0x00, 0x00, 0x5C, 0x2D, 0x4E, 0x38, 0xC1, 0xA4];
+        report.extend_from_slice(&[0x01, 0x00, 0xFF, 0x7F, 0x7F, 0x00, 0x00, 0x00]);
+        report.extend_from_slice(&[0; 6]);
+        report.push(data.len() as u8);
+        report.extend_from_slice(&data);
+        let mut event = vec![
+            H4_EVENT,
+            HCI_LE_META_EVENT,
+            0,
+            LE_EXTENDED_ADVERTISING_REPORT,
+            2,
+        ];
+        event.extend_from_slice(&report);
+        event.extend_from_slice(&report);
+        event[2] = event.len() as u8 - 3;
+
+        let advertisements = read_capture(&pcap(LINKTYPE_BLUETOOTH_HCI_H4, &[event])).unwrap();
+        let time = Duration::new(1_700_000_000, 250_000_000);
+        assert_eq!(advertisements, vec![expected(time, None); 2]);
+    }
+
+    #[test]
+    fn pcap_files() {
+        let time = Duration::new(1_700_000_000, 250_000_000);
+
+        let h4 = [&[0, 0, 0, 1, H4_EVENT][..], &report_event()].concat();
+        let capture = pcap(LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR, &[h4]);
+        assert_eq!(read_capture(&capture), Ok(vec![expected(time, Some(-70))]));
+
+        // channel 37, -60 dBm, signal power valid | ADV_IND | CRC
+        let data = decode_hex(ADVERTISEMENT).unwrap();
+        let mut ll = vec![37, -60i8 as u8, 0, 0, 0, 0, 0, 0, 0x02, 0x00];
+        ll.extend_from_slice(&ADVERTISING_ACCESS_ADDRESS.to_le_bytes());
+        ll.extend_from_slice(&[0x40, 6 + data.len() as u8]);
+        ll.extend_from_slice(&[0x5C, 0x2D, 0x4E, 0x38, 0xC1, 0xA4]);
+        ll.extend_from_slice(&data);
+        ll.extend_from_slice(&[0x12, 0x34, 0x56]);
+        // data channel PDU, skipped
+        let data_pdu = [&[37, 0, 0, 0, 0, 0, 0, 0, 0, 0][..], &[1, 2, 3, 4, 1, 0]].concat();
+
+        let capture = pcap(LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR, &[ll, data_pdu]);
+        assert_eq!(read_capture(&capture), Ok(vec![expected(time, Some(-60))]));
+
+        assert_eq!(
+            read_capture(&pcap(1, &[])),
+            Err(CaptureError::UnsupportedLinkType(1))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(
+            datalink in prop_oneof![Just(1001u32), Just(1002), Just(2001), Just(187), Just(201), Just(256)],
+            records in vec(any::<u8>(), 0..256),
+        ) {
+            let _ = read_capture(&records);
+            let _ = read_capture(&[&btsnoop(datalink, &[], 0)[..], &records].concat());
+            let _ = read_capture(&[&pcap(datalink, &[])[..], &records].concat());
+            let _ = read_capture(&btsnoop(datalink, &[(3, records.clone())], 0));
+            let _ = read_capture(&pcap(datalink, &[records]));
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Advertisement decoders of each sensor brand, behind a single entry point
+//!
+//! A `DecoderRegistry` tries its decoders in registration order and hands the advertisement to
+//! the first one matching one of its AD structures.
+
+use std::{fmt, sync::Arc};
+
+use crate::{
+    ad::{find_local_name, find_manufacturer_data, find_service_data, parse_ad, AdRecord},
+    atc::ATC_SERVICE_UUID,
+    bthome::BTHOME_SERVICE_UUID,
+    decode_atc,
+    govee::{parse_govee, GoveeModel, GOVEE_COMPANY_ID, GOVEE_H5179_COMPANY_ID},
+    inkbird::{parse_inkbird, INKBIRD_DATA_LEN, INKBIRD_LOCAL_NAMES},
+    mibeacon::MIBEACON_SERVICE_UUID,
+    parse_bthome,
+    qingping::{parse_qingping, QINGPING_SERVICE_UUID},
+    ruuvi::{parse_ruuvi, RUUVI_COMPANY_ID},
+    switchbot::{parse_switchbot, SWITCHBOT_COMPANY_ID, SWITCHBOT_SERVICE_UUID},
+    DecodeError, Decryptor, Measurement,
+};
+
+/// Decoder of the advertisements of a sensor brand or format
+pub trait AdvertisementDecoder: Send + Sync {
+    /// Format name, e.g. `MiBeacon`
+    fn name(&self) -> &'static str;
+
+    /// Whether the AD structure is one this decoder reads
+    fn matches(&self, record: &AdRecord) -> bool;
+
+    /// Decode an advertisement payload with a matching AD structure, `mac` being the advertiser
+    /// address in display order when known
+    fn decode(&self, mac: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError>;
+}
+
+/// Xiaomi MiBeacon, service data 0xFE95
+#[derive(Debug, Clone)]
+pub struct MiBeaconDecoder {
+    decryptor: Decryptor,
+}
+
+impl MiBeaconDecoder {
+    pub fn new(decryptor: Decryptor) -> Self {
+        MiBeaconDecoder { decryptor }
+    }
+}
+
+impl AdvertisementDecoder for MiBeaconDecoder {
+    fn name(&self) -> &'static str {
+        "MiBeacon"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ServiceData { uuid, .. } if *uuid == MIBEACON_SERVICE_UUID)
+    }
+
+    fn decode(&self, mac: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        Ok(vec![self.decryptor.decode_frame(mac, data)?.measurement])
+    }
+}
+
+/// ATC1441 / pvvx custom firmware, service data 0x181A
+#[derive(Debug, Clone)]
+pub struct AtcDecoder {
+    decryptor: Decryptor,
+}
+
+impl AtcDecoder {
+    pub fn new(decryptor: Decryptor) -> Self {
+        AtcDecoder { decryptor }
+    }
+}
+
+impl AdvertisementDecoder for AtcDecoder {
+    fn name(&self) -> &'static str {
+        "ATC"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ServiceData { uuid, .. } if *uuid == ATC_SERVICE_UUID)
+    }
+
+    fn decode(&self, mac: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        match mac {
+            Some(mac) => self.decryptor.decode_atc_frame(mac, data),
+            // only plain frames without the address to look up the key
+            None => {
+                let service_data =
+                    find_service_data(data, ATC_SERVICE_UUID).ok_or(DecodeError::NoServiceData)?;
+                Ok(decode_atc(service_data)?.measurements)
+            }
+        }
+    }
+}
+
+/// BTHome v2, service data 0xFCD2
+#[derive(Debug, Clone)]
+pub struct BtHomeDecoder {
+    decryptor: Decryptor,
+}
+
+impl BtHomeDecoder {
+    pub fn new(decryptor: Decryptor) -> Self {
+        BtHomeDecoder { decryptor }
+    }
+}
+
+impl AdvertisementDecoder for BtHomeDecoder {
+    fn name(&self) -> &'static str {
+        "BTHome"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ServiceData { uuid, .. } if *uuid == BTHOME_SERVICE_UUID)
+    }
+
+    fn decode(&self, mac: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        match mac {
+            Some(mac) => self.decryptor.decode_bthome_frame(mac, data),
+            None => {
+                let service_data = find_service_data(data, BTHOME_SERVICE_UUID)
+                    .ok_or(DecodeError::NoServiceData)?;
+                Ok(parse_bthome(service_data)?.measurements())
+            }
+        }
+    }
+}
+
+/// RuuviTag RAWv2, manufacturer data of company 0x0499
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RuuviDecoder;
+
+impl AdvertisementDecoder for RuuviDecoder {
+    fn name(&self) -> &'static str {
+        "Ruuvi"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ManufacturerData { company_id, .. } if *company_id == RUUVI_COMPANY_ID)
+    }
+
+    fn decode(&self, _: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        let data = find_manufacturer_data(data, RUUVI_COMPANY_ID)
+            .ok_or(DecodeError::NoManufacturerData)?;
+        Ok(parse_ruuvi(data)?.measurements())
+    }
+}
+
+/// Govee H5074, H5075 and H5179 thermo-hygrometers, manufacturer data
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GoveeDecoder;
+
+impl AdvertisementDecoder for GoveeDecoder {
+    fn name(&self) -> &'static str {
+        "Govee"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        match record {
+            AdRecord::ManufacturerData { company_id, .. } => {
+                matches!(*company_id, GOVEE_COMPANY_ID | GOVEE_H5179_COMPANY_ID)
+            }
+            _ => record
+                .local_name()
+                .is_some_and(|name| GoveeModel::from_local_name(name).is_some()),
+        }
+    }
+
+    fn decode(&self, _: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        let model = find_local_name(data)
+            .and_then(|name| GoveeModel::from_local_name(&name))
+            .or_else(|| {
+                parse_ad(data).find_map(|record| match record {
+                    AdRecord::ManufacturerData { company_id, data } => {
+                        GoveeModel::from_manufacturer_data(company_id, data.len())
+                    }
+                    _ => None,
+                })
+            })
+            .ok_or(DecodeError::UnknownFormat)?;
+        let data = find_manufacturer_data(data, model.company_id())
+            .ok_or(DecodeError::NoManufacturerData)?;
+
+        Ok(parse_govee(model, data)?.measurements())
+    }
+}
+
+/// Qingping (ClearGrass) CGDK2, CGP1W and CGG1, service data 0xFDCD
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QingpingDecoder;
+
+impl AdvertisementDecoder for QingpingDecoder {
+    fn name(&self) -> &'static str {
+        "Qingping"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ServiceData { uuid, .. } if *uuid == QINGPING_SERVICE_UUID)
+    }
+
+    fn decode(&self, _: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        let service_data =
+            find_service_data(data, QINGPING_SERVICE_UUID).ok_or(DecodeError::NoServiceData)?;
+        Ok(parse_qingping(service_data)?.measurements)
+    }
+}
+
+/// SwitchBot Meter, Meter Plus and Outdoor Meter, service data 0xFD3D and manufacturer data
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwitchBotDecoder;
+
+impl AdvertisementDecoder for SwitchBotDecoder {
+    fn name(&self) -> &'static str {
+        "SwitchBot"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        matches!(record, AdRecord::ServiceData { uuid, .. } if *uuid == SWITCHBOT_SERVICE_UUID)
+    }
+
+    fn decode(&self, _: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        let service_data =
+            find_service_data(data, SWITCHBOT_SERVICE_UUID).ok_or(DecodeError::NoServiceData)?;
+        let manufacturer_data = find_manufacturer_data(data, SWITCHBOT_COMPANY_ID);
+        Ok(parse_switchbot(service_data, manufacturer_data)?.measurements())
+    }
+}
+
+/// Inkbird IBS-TH1 and IBS-TH2, local name `sps` or `tps` and manufacturer data
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InkbirdDecoder;
+
+impl AdvertisementDecoder for InkbirdDecoder {
+    fn name(&self) -> &'static str {
+        "Inkbird"
+    }
+
+    fn matches(&self, record: &AdRecord) -> bool {
+        record
+            .local_name()
+            .is_some_and(|name| INKBIRD_LOCAL_NAMES.contains(&name))
+    }
+
+    fn decode(&self, _: Option<[u8; 6]>, data: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+        // the company id is the temperature, the data length tells the record
+        let (company_id, data) = parse_ad(data)
+            .find_map(|record| match record {
+                AdRecord::ManufacturerData { company_id, data }
+                    if data.len() == INKBIRD_DATA_LEN =>
+                {
+                    Some((company_id, data))
+                }
+                _ => None,
+            })
+            .ok_or(DecodeError::NoManufacturerData)?;
+        Ok(parse_inkbird(company_id, data)?.measurements())
+    }
+}
+
+/// Decoders tried in turn on each advertisement
+///
+/// Cloning shares the decoders, so a registry can be handed to other threads.
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<Arc<dyn AdvertisementDecoder>>,
+}
+
+impl DecoderRegistry {
+    /// A registry without decoders, see `register`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All the formats of this crate, with the keys of `decryptor`
+    pub fn with_defaults(decryptor: &Decryptor) -> Self {
+        let mut registry = Self::new();
+        registry.register(MiBeaconDecoder::new(decryptor.clone()));
+        registry.register(AtcDecoder::new(decryptor.clone()));
+        registry.register(BtHomeDecoder::new(decryptor.clone()));
+        registry.register(RuuviDecoder);
+        registry.register(GoveeDecoder);
+        registry.register(QingpingDecoder);
+        registry.register(SwitchBotDecoder);
+        registry.register(InkbirdDecoder);
+        registry
+    }
+
+    /// Add a decoder, tried after the ones already registered
+    pub fn register<D: AdvertisementDecoder + 'static>(&mut self, decoder: D) -> &mut Self {
+        self.decoders.push(Arc::new(decoder));
+        self
+    }
+
+    /// First decoder matching an AD structure of the advertisement payload
+    pub fn decoder(&self, data: &[u8]) -> Option<&dyn AdvertisementDecoder> {
+        self.decoders
+            .iter()
+            .find(|decoder| parse_ad(data).any(|record| decoder.matches(&record)))
+            .map(|decoder| decoder.as_ref())
+    }
+
+    /// Decode an advertisement payload whatever its format, `UnknownFormat` when no decoder
+    /// matches
+    pub fn decode_advertisement(
+        &self,
+        mac: Option<[u8; 6]>,
+        data: &[u8],
+    ) -> Result<Vec<Measurement>, DecodeError> {
+        self.decoder(data)
+            .ok_or(DecodeError::UnknownFormat)?
+            .decode(mac, data)
+    }
+}
+
+impl fmt::Debug for DecoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.decoders.iter().map(|decoder| decoder.name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_hex, encode_frame, Device, RelativeHumidity, Temperature};
+
+    const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+    fn device() -> Device {
+        Device {
+            mac: "A4:C1:38:4E:2D:5C".to_string(),
+            key: "000102030405060708090a0b0c0d0e0f".to_string(),
+            room: "Salon".to_string(),
+            model: None,
+        }
+    }
+
+    /// Any advertisement with manufacturer data of company 0xFFFF
+    struct TestDecoder;
+
+    impl AdvertisementDecoder for TestDecoder {
+        fn name(&self) -> &'static str {
+            "Test"
+        }
+
+        fn matches(&self, record: &AdRecord) -> bool {
+            matches!(record, AdRecord::ManufacturerData { company_id, .. } if *company_id == 0xFFFF)
+        }
+
+        fn decode(&self, _: Option<[u8; 6]>, _: &[u8]) -> Result<Vec<Measurement>, DecodeError> {
+            Ok(vec![Measurement::Co2(400)])
+        }
+    }
+
+    #[test]
+    fn routing() {
+        let decryptor = Decryptor::from_devices([device()]).unwrap();
+        let mut registry = DecoderRegistry::with_defaults(&decryptor);
+        let measurement = Measurement::Temperature(Temperature::from_decidegrees(215));
+
+        let mibeacon = encode_frame(&device(), 1, &measurement).unwrap();
+        assert_eq!(registry.decoder(&mibeacon).unwrap().name(), "MiBeacon");
+        assert_eq!(
+            registry.decode_advertisement(Some(MAC), &mibeacon),
+            Ok(vec![measurement])
+        );
+
+        let atc = decode_hex("02010610161A18a4c1380283f400a22f5f0bf819").unwrap();
+        assert_eq!(registry.decoder(&atc).unwrap().name(), "ATC");
+        assert_eq!(registry.decode_advertisement(None, &atc).unwrap().len(), 3);
+
+        let bthome = decode_hex("0201060A16D2FC4002CA0903BF13").unwrap();
+        assert_eq!(registry.decoder(&bthome).unwrap().name(), "BTHome");
+        assert_eq!(
+            registry.decode_advertisement(None, &bthome).unwrap().len(),
+            2
+        );
+
+        let ruuvi =
+            decode_hex("0201061BFF99040512FC5394C37C0004FFFC040CAC364200CDCBB8334C884F").unwrap();
+        assert_eq!(registry.decoder(&ruuvi).unwrap().name(), "Ruuvi");
+        assert_eq!(
+            registry.decode_advertisement(None, &ruuvi).unwrap().len(),
+            3
+        );
+
+        let govee = decode_hex(concat!(
+            "0D09475648353037355F32373632", // GVH5075_2762
+            "09FF88EC000341C26400",
+        ))
+        .unwrap();
+        assert_eq!(registry.decoder(&govee).unwrap().name(), "Govee");
+        assert_eq!(
+            registry.decode_advertisement(None, &govee).unwrap().len(),
+            2
+        );
+        // no name, told by the data length
+        let govee = decode_hex("0AFF88EC00A5088B156402").unwrap();
+        assert_eq!(
+            registry.decode_advertisement(None, &govee).unwrap().len(),
+            2
+        );
+
+        let qingping = decode_hex("1816CDFD0809C46DB018B7E70104EE00FE0102015A07021A27").unwrap();
+        assert_eq!(registry.decoder(&qingping).unwrap().name(), "Qingping");
+        assert_eq!(
+            registry
+                .decode_advertisement(None, &qingping)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let switchbot = decode_hex("02010609163DFD540064059630").unwrap();
+        assert_eq!(registry.decoder(&switchbot).unwrap().name(), "SwitchBot");
+        assert_eq!(
+            registry
+                .decode_advertisement(None, &switchbot)
+                .unwrap()
+                .len(),
+            2
+        );
+        // Outdoor Meter, readings in the manufacturer data
+        let switchbot = decode_hex("06163DFD7700640EFF6909CB5A8C1E0F426C00048C3A").unwrap();
+        assert_eq!(
+            registry.decode_advertisement(None, &switchbot).unwrap()[0],
+            Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(124),
+                humidity: RelativeHumidity::from_decipercent(580),
+            }
+        );
+
+        let inkbird = decode_hex("04097370730AFF29095A140055495508").unwrap();
+        assert_eq!(registry.decoder(&inkbird).unwrap().name(), "Inkbird");
+        assert_eq!(
+            registry.decode_advertisement(None, &inkbird).unwrap().len(),
+            2
+        );
+        // IBS-TH2, no humidity sensor
+        let inkbird = decode_hex("04097470730AFF66080000002E685A08").unwrap();
+        assert_eq!(registry.decoder(&inkbird).unwrap().name(), "Inkbird");
+        assert_eq!(
+            registry.decode_advertisement(None, &inkbird).unwrap()[0],
+            Measurement::Temperature(Temperature::from_decidegrees(215))
+        );
+
+        let other = decode_hex("02010605FFFFFF0102").unwrap();
+        assert_eq!(
+            registry.decode_advertisement(None, &other),
+            Err(DecodeError::UnknownFormat)
+        );
+        registry.register(TestDecoder);
+        assert_eq!(
+            registry.decode_advertisement(None, &other),
+            Ok(vec![Measurement::Co2(400)])
+        );
+        assert_eq!(
+            format!("{:?}", registry),
+            r#"["MiBeacon", "ATC", "BTHome", "Ruuvi", "Govee", "Qingping", "SwitchBot", "Inkbird", "Test"]"#
+        );
+    }
+
+    #[test]
+    fn missing_data() {
+        let flags = decode_hex("020106").unwrap();
+        assert_eq!(
+            RuuviDecoder.decode(None, &flags),
+            Err(DecodeError::NoManufacturerData)
+        );
+        // model told by the name only
+        let govee = decode_hex("0D09475648353037355F32373632").unwrap();
+        assert_eq!(
+            GoveeDecoder.decode(None, &govee),
+            Err(DecodeError::NoManufacturerData)
+        );
+        let inkbird = decode_hex("0409737073").unwrap();
+        assert_eq!(
+            InkbirdDecoder.decode(None, &inkbird),
+            Err(DecodeError::NoManufacturerData)
+        );
+    }
+}
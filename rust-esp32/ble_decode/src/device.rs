@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A registered sensor and its bind key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Device {
+    /// `AA:BB:CC:DD:EE:FF`
+    pub mac: String,
+    /// 32 hex chars bind key, 24 for legacy (MiBeacon v2/v3) devices
+    pub key: String,
+    #[serde(default)]
+    pub room: String,
+    /// Xiaomi model, e.g. `miaomiaoce.sensor_ht.t2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// TOML registries are a list of `[[devices]]` tables
+#[derive(Serialize, Deserialize)]
+struct DevicesToml {
+    devices: Vec<Device>,
+}
+
+pub(crate) fn parse_json(s: &str) -> anyhow::Result<Vec<Device>> {
+    Ok(serde_json::from_str(s)?)
+}
+
+pub(crate) fn parse_toml(s: &str) -> anyhow::Result<Vec<Device>> {
+    let file: DevicesToml = toml::from_str(s)?;
+    Ok(file.devices)
+}
+
+pub(crate) fn to_json(devices: &[Device]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(devices)?)
+}
+
+pub(crate) fn to_toml(devices: &[Device]) -> anyhow::Result<String> {
+    Ok(toml::to_string(&DevicesToml {
+        devices: devices.to_vec(),
+    })?)
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("toml")
+}
+
+/// Devices of a `.toml` or `.json` registry file
+pub fn load_devices<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<Device>> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+
+    if is_toml(path) {
+        parse_toml(&content)
+    } else {
+        parse_json(&content)
+    }
+}
+
+/// Content of a registry file, TOML or JSON depending on the extension of `path`
+pub fn format_devices<P: AsRef<Path>>(path: P, devices: &[Device]) -> anyhow::Result<String> {
+    if is_toml(path.as_ref()) {
+        to_toml(devices)
+    } else {
+        Ok(to_json(devices)? + "\n")
+    }
+}
+
+/// Write a registry file, TOML or JSON depending on its extension
+pub fn save_devices<P: AsRef<Path>>(path: P, devices: &[Device]) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    Ok(std::fs::write(path, format_devices(path, devices)?)?)
+}
+
+/// Registry lookups are done on the upper case MAC
+pub(crate) fn normalize_mac(mac: &str) -> String {
+    mac.trim().to_ascii_uppercase().replace('-', ":")
+}
+
+/// `AA:BB:CC:DD:EE:FF` (or `-` separated) to bytes in display order
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let mac = normalize_mac(mac);
+    let mut bytes = [0u8; 6];
+    let mut parts = mac.split(':');
+
+    for byte in bytes.iter_mut() {
+        let part = parts.next()?;
+        // from_str_radix would also take a sign, e.g. `+F`
+        if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+
+    parts.next().is_none().then_some(bytes)
+}
+
+/// Bytes in display order to `AA:BB:CC:DD:EE:FF`
+pub fn format_mac(mac: [u8; 6]) -> String {
+    format!(
+        "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    )
+}
@@ -0,0 +1,295 @@
+//! MiBeacon v5 encoder, to build encrypted test frames and simulate sensors
+
+use ccm::aead::{generic_array::GenericArray, Aead, Payload};
+
+use crate::{
+    device::parse_mac,
+    mibeacon::{
+        FrameControl, MiBeaconFrame, MIBEACON_SERVICE_UUID, OBJECT_BATTERY, OBJECT_BUTTON,
+        OBJECT_CONDUCTIVITY, OBJECT_DOOR, OBJECT_FORMALDEHYDE, OBJECT_HUMIDITY, OBJECT_ILLUMINANCE,
+        OBJECT_LIGHT, OBJECT_MOISTURE, OBJECT_MOTION_ILLUMINANCE, OBJECT_NO_MOTION,
+        OBJECT_TEMPERATURE, OBJECT_TEMPERATURE_HUMIDITY, OBJECT_WATER_LEAK,
+    },
+    Aes128Ccm, ButtonPress, DecodeError, Device, DeviceKey, DoorState, Event, Measurement,
+};
+
+/// LYWSD03MMC
+pub const DEFAULT_PRODUCT_ID: u16 = 0x055B;
+
+/// v5, encrypted, MAC and object included, as sent by the LYWSD03MMC
+const FRAME_CONTROL: u16 = 0x5858;
+
+/// Build the advertisement payload (flags and MiBeacon service data) a sensor would send
+///
+/// `counter` low byte is the frame counter, the upper bytes the ext. counter.
+pub fn encode_frame(
+    device: &Device,
+    counter: u32,
+    measurement: &Measurement,
+) -> Result<Vec<u8>, DecodeError> {
+    let mac = parse_mac(&device.mac).ok_or(DecodeError::InvalidMac)?;
+    let key = DeviceKey::from_hex(&device.key)?;
+    let object = encode_object(measurement).ok_or(DecodeError::UnsupportedMeasurement)?;
+
+    let service_data = encode_service_data(mac, &key, DEFAULT_PRODUCT_ID, counter, &object)?;
+    let uuid = MIBEACON_SERVICE_UUID.to_le_bytes();
+
+    let mut advertisement = vec![0x02, 0x01, 0x06, service_data.len() as u8 + 3, 0x16];
+    advertisement.extend_from_slice(&uuid);
+    advertisement.extend_from_slice(&service_data);
+    Ok(advertisement)
+}
+
+/// Encrypt a plain object into MiBeacon v5 service data
+pub fn encode_service_data(
+    mac: [u8; 6],
+    key: &DeviceKey,
+    product_id: u16,
+    counter: u32,
+    object: &[u8],
+) -> Result<Vec<u8>, DecodeError> {
+    let counter = counter.to_le_bytes();
+    let frame = MiBeaconFrame {
+        frame_control: FrameControl(FRAME_CONTROL),
+        product_id,
+        frame_counter: counter[0],
+        mac: Some(mac),
+        capability: None,
+        io_capability: None,
+        object,
+        ext_counter: Some([counter[1], counter[2], counter[3]]),
+        mic: None,
+    };
+    let nonce = frame.nonce().ok_or(DecodeError::MissingMac)?;
+
+    let cipher: Aes128Ccm = key.ccm()?;
+    let encrypted = cipher
+        .encrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: object,
+                aad: &[0x11],
+            },
+        )
+        .map_err(|_| DecodeError::InvalidKey)?;
+    let (encrypted, mic) = encrypted.split_at(object.len());
+
+    let mut data = Vec::with_capacity(5 + 6 + object.len() + 7);
+    data.extend_from_slice(&FRAME_CONTROL.to_le_bytes());
+    data.extend_from_slice(&product_id.to_le_bytes());
+    data.push(counter[0]);
+    data.extend(mac.iter().rev());
+    data.extend_from_slice(encrypted);
+    data.extend_from_slice(&counter[1..]);
+    data.extend_from_slice(mic);
+    Ok(data)
+}
+
+/// Plain object : id (2 bytes) | length (1 byte) | value, `None` without MiBeacon object
+pub fn encode_object(measurement: &Measurement) -> Option<Vec<u8>> {
+    let (id, value) = match measurement {
+        Measurement::Temperature(t) => (OBJECT_TEMPERATURE, t.decidegrees().to_le_bytes().to_vec()),
+        Measurement::Humidity(h) => (OBJECT_HUMIDITY, h.decipercent().to_le_bytes().to_vec()),
+        Measurement::TemperatureHumidity {
+            temperature,
+            humidity,
+        } => (
+            OBJECT_TEMPERATURE_HUMIDITY,
+            [
+                temperature.decidegrees().to_le_bytes(),
+                humidity.decipercent().to_le_bytes(),
+            ]
+            .concat(),
+        ),
+        Measurement::Battery(b) => (OBJECT_BATTERY, vec![b.percent()]),
+        // 3 bytes on air
+        Measurement::Illuminance(lux) if *lux <= 0xFF_FFFF => {
+            (OBJECT_ILLUMINANCE, lux.to_le_bytes()[..3].to_vec())
+        }
+        Measurement::Moisture(m) => (OBJECT_MOISTURE, vec![*m]),
+        Measurement::Conductivity(c) => (OBJECT_CONDUCTIVITY, c.to_le_bytes().to_vec()),
+        Measurement::Formaldehyde(f) => (OBJECT_FORMALDEHYDE, f.to_le_bytes().to_vec()),
+        Measurement::Event(event) => encode_event(event)?,
+        _ => return None,
+    };
+
+    let mut object = id.to_le_bytes().to_vec();
+    object.push(value.len() as u8);
+    object.extend_from_slice(&value);
+    Some(object)
+}
+
+fn encode_event(event: &Event) -> Option<(u16, Vec<u8>)> {
+    let object = match *event {
+        Event::Button { index, press } => {
+            let press = match press {
+                ButtonPress::Single => 0,
+                ButtonPress::Double => 1,
+                ButtonPress::Long => 2,
+                ButtonPress::Triple => 3,
+            };
+            let index = index.to_le_bytes();
+            (OBJECT_BUTTON, vec![index[0], index[1], press])
+        }
+        Event::Motion { illuminance } if illuminance <= 0xFF_FFFF => (
+            OBJECT_MOTION_ILLUMINANCE,
+            illuminance.to_le_bytes()[..3].to_vec(),
+        ),
+        Event::Motion { .. } => return None,
+        Event::NoMotion { seconds } => (OBJECT_NO_MOTION, seconds.to_le_bytes().to_vec()),
+        Event::Door(state) => {
+            let state = match state {
+                DoorState::Open => 0,
+                DoorState::Closed => 1,
+                DoorState::LeftOpen => 2,
+                DoorState::Reset => 3,
+            };
+            (OBJECT_DOOR, vec![state])
+        }
+        Event::Light(light) => (OBJECT_LIGHT, vec![light as u8]),
+        Event::WaterLeak(wet) => (OBJECT_WATER_LEAK, vec![wet as u8]),
+    };
+    Some(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_hex, decode_object, parse_mibeacon, BatteryPercent, Decryptor, FrameStatus,
+        FrameTracker, RelativeHumidity, Temperature, REPLAY_WINDOW,
+    };
+    use proptest::prelude::*;
+
+    fn device() -> Device {
+        Device {
+            mac: "A4:C1:38:4E:2D:5C".to_string(),
+            key: "000102030405060708090a0b0c0d0e0f".to_string(),
+            room: "Test".to_string(),
+            model: None,
+        }
+    }
+
+    #[test]
+    fn frame_layout() {
+        let measurement = Measurement::Temperature(Temperature::from_decidegrees(236));
+        let data = encode_frame(&device(), 0x0102_034F, &measurement).unwrap();
+
+        // same header and length as the captured LYWSD03MMC frames
+        assert_eq!(data.len(), 30);
+        assert_eq!(
+            &data[..18],
+            &decode_hex("0201061A1695FE58585B054F5C2D4E38C1A4").unwrap()[..]
+        );
+
+        let frame = parse_mibeacon(&data[7..]).unwrap();
+        assert_eq!(frame.ext_counter, Some([0x03, 0x02, 0x01]));
+        assert_eq!(frame.object.len(), 5);
+
+        let key = decode_hex(&device().key).unwrap();
+        assert_eq!(
+            decode_object(&frame.decrypt(&key).unwrap()),
+            Ok(measurement)
+        );
+    }
+
+    #[test]
+    fn encode_errors() {
+        let measurement = Measurement::Battery(BatteryPercent::new(80));
+
+        let mut bad_mac = device();
+        bad_mac.mac = "A4:C1:38:4E:2D".to_string();
+        assert_eq!(
+            encode_frame(&bad_mac, 0, &measurement),
+            Err(DecodeError::InvalidMac)
+        );
+
+        let mut bad_key = device();
+        bad_key.key = "0001".to_string();
+        assert_eq!(
+            encode_frame(&bad_key, 0, &measurement),
+            Err(DecodeError::InvalidKey)
+        );
+
+        assert_eq!(
+            encode_frame(&device(), 0, &Measurement::Co2(400)),
+            Err(DecodeError::UnsupportedMeasurement)
+        );
+        assert_eq!(encode_object(&Measurement::Illuminance(0x100_0000)), None);
+    }
+
+    fn measurement() -> impl Strategy<Value = Measurement> {
+        prop_oneof![
+            any::<i16>().prop_map(|t| Measurement::Temperature(Temperature::from_decidegrees(t))),
+            any::<u16>().prop_map(|h| Measurement::Humidity(RelativeHumidity::from_decipercent(h))),
+            (any::<i16>(), any::<u16>()).prop_map(|(t, h)| Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(t),
+                humidity: RelativeHumidity::from_decipercent(h),
+            }),
+            any::<u8>().prop_map(|b| Measurement::Battery(BatteryPercent::new(b))),
+            (0..=0xFF_FFFFu32).prop_map(Measurement::Illuminance),
+            any::<u8>().prop_map(Measurement::Moisture),
+            any::<u16>().prop_map(Measurement::Conductivity),
+            any::<u16>().prop_map(Measurement::Formaldehyde),
+            event().prop_map(Measurement::Event),
+        ]
+    }
+
+    fn event() -> impl Strategy<Value = Event> {
+        let press = prop_oneof![
+            Just(ButtonPress::Single),
+            Just(ButtonPress::Double),
+            Just(ButtonPress::Long),
+            Just(ButtonPress::Triple),
+        ];
+        let door = prop_oneof![
+            Just(DoorState::Open),
+            Just(DoorState::Closed),
+            Just(DoorState::LeftOpen),
+            Just(DoorState::Reset),
+        ];
+        prop_oneof![
+            (any::<u16>(), press).prop_map(|(index, press)| Event::Button { index, press }),
+            (0..=0xFF_FFFFu32).prop_map(|illuminance| Event::Motion { illuminance }),
+            any::<u32>().prop_map(|seconds| Event::NoMotion { seconds }),
+            door.prop_map(Event::Door),
+            any::<bool>().prop_map(Event::Light),
+            any::<bool>().prop_map(Event::WaterLeak),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(counter in any::<u32>(), measurement in measurement()) {
+            let data = encode_frame(&device(), counter, &measurement).unwrap();
+            let decryptor = Decryptor::from_devices([device()]).unwrap();
+
+            prop_assert_eq!(decryptor.decode_frame_data(&data), Ok(measurement));
+        }
+
+        #[test]
+        fn counter_order(counter in any::<u32>(), step in 1..0x8000_0000u32) {
+            let measurement = Measurement::Battery(BatteryPercent::new(50));
+            let first = encode_frame(&device(), counter, &measurement).unwrap();
+            let next = encode_frame(&device(), counter.wrapping_add(step), &measurement).unwrap();
+            let mac = parse_mac(&device().mac).unwrap();
+            let mut tracker = FrameTracker::new();
+
+            prop_assert_eq!(tracker.check_advertisement(mac, &first), Some(FrameStatus::New));
+            tracker.commit_advertisement(mac, &first);
+            prop_assert_eq!(tracker.check_advertisement(mac, &next), Some(FrameStatus::New));
+            tracker.commit_advertisement(mac, &next);
+
+            // going back is never accepted, even once the old frame is authenticated
+            let back = if step <= REPLAY_WINDOW {
+                FrameStatus::Replayed
+            } else {
+                FrameStatus::Reset
+            };
+            prop_assert_eq!(tracker.check_advertisement(mac, &first), Some(back));
+            tracker.commit_advertisement(mac, &first);
+            prop_assert_eq!(tracker.check_advertisement(mac, &first), Some(back));
+            prop_assert_eq!(tracker.check_advertisement(mac, &next), Some(FrameStatus::Duplicate));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::format_mac;
+
+/// Why an advertisement could not be turned into a measurement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The advertisement has no service data for the expected UUID
+    NoServiceData,
+    /// The advertisement has no manufacturer data for the expected company
+    NoManufacturerData,
+    /// The frame ends before a field announced by its header
+    FrameTooShort,
+    /// The frame does not carry the device MAC
+    MissingMac,
+    /// No key registered for this MAC
+    UnknownDevice([u8; 6]),
+    /// The registered MAC is not `AA:BB:CC:DD:EE:FF`
+    InvalidMac,
+    /// The registered key is not 12 (legacy) or 16 bytes of hex
+    InvalidKey,
+    /// AES-CCM authentication failed, most likely a wrong key
+    TagMismatch,
+    /// The checksum carried by the frame does not match its content
+    ChecksumMismatch,
+    /// The frame is not encrypted
+    Unencrypted,
+    /// The frame is encrypted and needs the device key
+    Encrypted,
+    /// The frame length or header matches no known format
+    UnknownFormat,
+    /// The frame does not carry any object
+    NoObject,
+    /// Object id not handled by this library
+    UnsupportedObject(u16),
+    /// Object length does not match its id
+    InvalidObject(u16),
+    /// Object value out of its documented range
+    InvalidValue(u16),
+    /// The measurement has no MiBeacon object to encode it
+    UnsupportedMeasurement,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NoServiceData => write!(f, "no service data"),
+            DecodeError::NoManufacturerData => write!(f, "no manufacturer data"),
+            DecodeError::FrameTooShort => write!(f, "frame too short"),
+            DecodeError::MissingMac => write!(f, "MAC not included in frame"),
+            DecodeError::UnknownDevice(mac) => write!(f, "unknown device {}", format_mac(*mac)),
+            DecodeError::InvalidMac => write!(f, "invalid device MAC"),
+            DecodeError::InvalidKey => write!(f, "invalid device key"),
+            DecodeError::TagMismatch => write!(f, "CCM tag mismatch (wrong key?)"),
+            DecodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            DecodeError::Unencrypted => write!(f, "unencrypted frame"),
+            DecodeError::Encrypted => write!(f, "encrypted frame"),
+            DecodeError::UnknownFormat => write!(f, "unknown frame format"),
+            DecodeError::NoObject => write!(f, "no object in frame"),
+            DecodeError::UnsupportedObject(id) => write!(f, "unsupported object 0x{:04X}", id),
+            DecodeError::InvalidObject(id) => write!(f, "invalid length for object 0x{:04X}", id),
+            DecodeError::InvalidValue(id) => write!(f, "invalid value for object 0x{:04X}", id),
+            DecodeError::UnsupportedMeasurement => write!(f, "measurement has no MiBeacon object"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Why a hex string could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    /// Odd number of digits
+    OddLength,
+    /// Not an hex digit, at this byte offset
+    InvalidDigit(usize),
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::OddLength => write!(f, "odd number of hex digits"),
+            HexError::InvalidDigit(offset) => write!(f, "invalid hex digit at {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
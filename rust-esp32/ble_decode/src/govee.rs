@@ -0,0 +1,197 @@
+//! Govee thermo-hygrometer advertisements (manufacturer data)
+//!
+//! Each model packs its readings its own way, after the company id:
+//!
+//! ```text
+//! H5075 (0xEC88, 6) : 0 | u24 BE 0.1°C * 1000 + 0.1%, bit 23 negative | batt u8 % | 0
+//! H5074 (0xEC88, 7) : 0 | temp i16 LE 0.01°C | humi u16 LE 0.01% | batt u8 % | flags
+//! H5179 (0x8801, 9) : 4 bytes | temp i16 LE 0.01°C | humi u16 LE 0.01% | batt u8 %
+//! ```
+//!
+//! The model is told by the local name (`GVH5075_XXXX`, `Govee_H5074_XXXX`...) or, failing that,
+//! by the company id and data length.
+
+use crate::{BatteryPercent, DecodeError, Measurement, RelativeHumidity, Temperature};
+
+/// Company id of the H5074 and H5075 manufacturer data
+pub const GOVEE_COMPANY_ID: u16 = 0xEC88;
+/// Company id of the H5179 manufacturer data
+pub const GOVEE_H5179_COMPANY_ID: u16 = 0x8801;
+
+const H5075_LEN: usize = 6;
+const H5074_LEN: usize = 7;
+const H5179_LEN: usize = 9;
+
+const H5075_NEGATIVE: u32 = 0x80_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoveeModel {
+    H5074,
+    H5075,
+    H5179,
+}
+
+impl GoveeModel {
+    pub const ALL: [GoveeModel; 3] = [GoveeModel::H5074, GoveeModel::H5075, GoveeModel::H5179];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GoveeModel::H5074 => "H5074",
+            GoveeModel::H5075 => "H5075",
+            GoveeModel::H5179 => "H5179",
+        }
+    }
+
+    pub fn company_id(&self) -> u16 {
+        match self {
+            GoveeModel::H5074 | GoveeModel::H5075 => GOVEE_COMPANY_ID,
+            GoveeModel::H5179 => GOVEE_H5179_COMPANY_ID,
+        }
+    }
+
+    /// Model of a local name such as `GVH5075_1A2B`
+    pub fn from_local_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| name.contains(model.name()))
+    }
+
+    /// Model sending manufacturer data of this company id and length
+    pub fn from_manufacturer_data(company_id: u16, len: usize) -> Option<Self> {
+        match (company_id, len) {
+            (GOVEE_COMPANY_ID, H5075_LEN) => Some(GoveeModel::H5075),
+            (GOVEE_COMPANY_ID, H5074_LEN) => Some(GoveeModel::H5074),
+            (GOVEE_H5179_COMPANY_ID, H5179_LEN) => Some(GoveeModel::H5179),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded Govee advertisement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoveeFrame {
+    pub model: GoveeModel,
+    pub temperature: Temperature,
+    pub humidity: RelativeHumidity,
+    pub battery: BatteryPercent,
+}
+
+impl GoveeFrame {
+    pub fn measurements(&self) -> Vec<Measurement> {
+        vec![
+            Measurement::TemperatureHumidity {
+                temperature: self.temperature,
+                humidity: self.humidity,
+            },
+            Measurement::Battery(self.battery),
+        ]
+    }
+}
+
+/// Decode the manufacturer data of a `model`, without the company id
+pub fn parse_govee(model: GoveeModel, data: &[u8]) -> Result<GoveeFrame, DecodeError> {
+    let (temperature, humidity, battery) = match (model, data) {
+        (GoveeModel::H5075, [_, a, b, c, battery, ..]) => {
+            let packed = u32::from_be_bytes([0, *a, *b, *c]);
+            let value = packed & !H5075_NEGATIVE;
+            let decidegrees = (value / 1000) as i16;
+            let temperature = if packed & H5075_NEGATIVE != 0 {
+                -decidegrees
+            } else {
+                decidegrees
+            };
+            (
+                Temperature::from_decidegrees(temperature),
+                RelativeHumidity::from_decipercent((value % 1000) as u16),
+                *battery,
+            )
+        }
+        (GoveeModel::H5074, [_, t0, t1, h0, h1, battery, ..])
+        | (GoveeModel::H5179, [_, _, _, _, t0, t1, h0, h1, battery, ..]) => (
+            Temperature::from_centidegrees(i16::from_le_bytes([*t0, *t1])),
+            RelativeHumidity::from_centipercent(u16::from_le_bytes([*h0, *h1])),
+            *battery,
+        ),
+        _ => return Err(DecodeError::FrameTooShort),
+    };
+
+    Ok(GoveeFrame {
+        model,
+        temperature,
+        humidity,
+        battery: BatteryPercent::new(battery),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn models() {
+        assert_eq!(
+            GoveeModel::from_local_name("GVH5075_2762"),
+            Some(GoveeModel::H5075)
+        );
+        assert_eq!(
+            GoveeModel::from_local_name("Govee_H5179_A1B2"),
+            Some(GoveeModel::H5179)
+        );
+        assert_eq!(GoveeModel::from_local_name("LYWSD03MMC"), None);
+        assert_eq!(
+            GoveeModel::from_manufacturer_data(0xEC88, 7),
+            Some(GoveeModel::H5074)
+        );
+        assert_eq!(GoveeModel::from_manufacturer_data(0xEC88, 8), None);
+    }
+
+    #[test]
+    fn frames() {
+        let h5075 = parse_govee(GoveeModel::H5075, &decode_hex("000341C26400").unwrap()).unwrap();
+        assert_eq!(h5075.temperature, Temperature::from_decidegrees(213));
+        assert_eq!(h5075.humidity, RelativeHumidity::from_decipercent(442));
+        assert_eq!(h5075.battery, BatteryPercent::new(100));
+
+        // negative flag: -2.5°C 45.0%
+        let frozen = parse_govee(GoveeModel::H5075, &decode_hex("0080636A5500").unwrap()).unwrap();
+        assert_eq!(frozen.temperature, Temperature::from_decidegrees(-25));
+        assert_eq!(frozen.humidity, RelativeHumidity::from_decipercent(450));
+        assert_eq!(frozen.battery, BatteryPercent::new(85));
+
+        let h5074 = parse_govee(GoveeModel::H5074, &decode_hex("00A5088B156402").unwrap()).unwrap();
+        assert_eq!(
+            h5074.measurements(),
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(221),
+                    humidity: RelativeHumidity::from_decipercent(552),
+                },
+                Measurement::Battery(BatteryPercent::new(100)),
+            ]
+        );
+
+        let h5179 = parse_govee(
+            GoveeModel::H5179,
+            &decode_hex("EC0001010A0AA40664").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(h5179.temperature, Temperature::from_decidegrees(257));
+        assert_eq!(h5179.humidity, RelativeHumidity::from_decipercent(170));
+
+        assert_eq!(
+            parse_govee(GoveeModel::H5179, &decode_hex("000341C26400").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(data in vec(any::<u8>(), 0..12)) {
+            for model in GoveeModel::ALL {
+                let _ = parse_govee(model, &data);
+            }
+        }
+    }
+}
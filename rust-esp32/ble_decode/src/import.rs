@@ -0,0 +1,267 @@
+//! Import of the Xiaomi-cloud-tokens-extractor text output (see `run_docker.sh`)
+//!
+//! The extractor prints one block per device, separated by dashes:
+//!
+//! ```text
+//!    NAME:     Salon
+//!    ID:       blt.3.1abcdefgh
+//!    MAC:      A4:C1:38:4E:2D:5C
+//!    TOKEN:    00112233445566778899aabb
+//!    BLE KEY:  00112233445566778899aabbccddeeff
+//!    MODEL:    miaomiaoce.sensor_ht.t2
+//!    ---------
+//! ```
+//!
+//! Only the BLE devices, the ones with a `BLE KEY`, are imported.
+
+use crate::{
+    device::{normalize_mac, parse_mac},
+    DecodeError, Device,
+};
+
+/// A device block of the extractor output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedDevice {
+    pub name: String,
+    pub model: Option<String>,
+    pub mac: Option<String>,
+    pub ble_key: Option<String>,
+}
+
+impl ExtractedDevice {
+    pub fn is_ble(&self) -> bool {
+        self.ble_key.is_some()
+    }
+
+    /// Registry entry with the name as room, `InvalidMac` / `InvalidKey` when the MAC or the
+    /// 32 hex chars bind key is missing or malformed
+    pub fn to_device(&self) -> Result<Device, DecodeError> {
+        let mac = self.mac.as_deref().ok_or(DecodeError::InvalidMac)?;
+        parse_mac(mac).ok_or(DecodeError::InvalidMac)?;
+
+        let key = self.ble_key.as_deref().ok_or(DecodeError::InvalidKey)?;
+        if key.len() != 32 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(DecodeError::InvalidKey);
+        }
+
+        Ok(Device {
+            mac: normalize_mac(mac),
+            key: key.to_ascii_lowercase(),
+            room: self.name.clone(),
+            model: self.model.clone(),
+        })
+    }
+}
+
+/// All the device blocks of the extractor output, BLE or not
+pub fn parse_extractor_output(text: &str) -> Vec<ExtractedDevice> {
+    let mut devices = Vec::new();
+    let mut current: Option<ExtractedDevice> = None;
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("---") {
+            devices.extend(current.take());
+            continue;
+        }
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+
+        match field.trim() {
+            "NAME" => {
+                // a new block, even without separator
+                devices.extend(current.take());
+                current = Some(ExtractedDevice {
+                    name: value,
+                    ..ExtractedDevice::default()
+                });
+            }
+            "MAC" => current.get_or_insert_with(Default::default).mac = Some(value),
+            "BLE KEY" => current.get_or_insert_with(Default::default).ble_key = Some(value),
+            "MODEL" => current.get_or_insert_with(Default::default).model = Some(value),
+            _ => {}
+        }
+    }
+    devices.extend(current);
+
+    devices
+}
+
+/// What `merge_devices` did to the registry, listing MACs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// BLE devices rejected, by name
+    pub skipped: Vec<(String, DecodeError)>,
+}
+
+/// Merge the BLE devices of the extractor output into a registry
+///
+/// Entries are matched on MAC: their key and model are updated, their room is kept unless
+/// empty, in which case the device name is used.
+pub fn merge_devices(registry: &mut Vec<Device>, extracted: &[ExtractedDevice]) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+
+    for extracted in extracted.iter().filter(|device| device.is_ble()) {
+        let imported = match extracted.to_device() {
+            Ok(device) => device,
+            Err(e) => {
+                summary.skipped.push((extracted.name.clone(), e));
+                continue;
+            }
+        };
+        let mac = parse_mac(&imported.mac);
+
+        let Some(existing) = registry
+            .iter_mut()
+            .find(|device| parse_mac(&device.mac) == mac)
+        else {
+            summary.added.push(imported.mac.clone());
+            registry.push(imported);
+            continue;
+        };
+
+        let mut merged = Device {
+            mac: existing.mac.clone(),
+            room: existing.room.clone(),
+            ..imported
+        };
+        if merged.room.trim().is_empty() {
+            merged.room = extracted.name.clone();
+        }
+
+        if *existing == merged {
+            summary.unchanged.push(merged.mac);
+        } else {
+            summary.updated.push(merged.mac.clone());
+            *existing = merged;
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const OUTPUT: &str = r#"
+Logging in...
+Logged in.
+
+Devices found for server "de" @ home "123456789":
+   ---------
+   NAME:     Salon
+   ID:       blt.3.1abcdefgh
+   MAC:      a4:c1:38:4e:2d:5c
+   TOKEN:    00112233445566778899aabb
+   BLE KEY:  00112233445566778899AABBCCDDEEFF
+   MODEL:    miaomiaoce.sensor_ht.t2
+   ---------
+   NAME:     Gateway
+   ID:       123456789
+   MAC:      04:CF:8C:00:00:01
+   IP:       192.168.1.10
+   TOKEN:    ffeeddccbbaa99887766554433221100
+   MODEL:    lumi.gateway.v3
+   ---------
+   NAME:     Chambre
+   ID:       blt.3.2abcdefgh
+   MAC:      A4:C1:38:CD:F2:86
+   BLE KEY:  0011
+   MODEL:    miaomiaoce.sensor_ht.t2
+   ---------
+   NAME:     Bébé
+   ID:       blt.3.3abcdefgh
+   MAC:      A4:C1:38:D7:70:32
+   BLE KEY:  ffeeddccbbaa99887766554433221100
+   MODEL:    miaomiaoce.sensor_ht.t2
+   ---------
+"#;
+
+    #[test]
+    fn parse_output() {
+        let devices = parse_extractor_output(OUTPUT);
+
+        assert_eq!(devices.len(), 4);
+        assert_eq!(
+            devices[0],
+            ExtractedDevice {
+                name: "Salon".to_string(),
+                model: Some("miaomiaoce.sensor_ht.t2".to_string()),
+                mac: Some("a4:c1:38:4e:2d:5c".to_string()),
+                ble_key: Some("00112233445566778899AABBCCDDEEFF".to_string()),
+            }
+        );
+        assert!(!devices[1].is_ble());
+        assert_eq!(
+            devices[0].to_device().unwrap(),
+            Device {
+                mac: "A4:C1:38:4E:2D:5C".to_string(),
+                key: "00112233445566778899aabbccddeeff".to_string(),
+                room: "Salon".to_string(),
+                model: Some("miaomiaoce.sensor_ht.t2".to_string()),
+            }
+        );
+        assert_eq!(devices[2].to_device(), Err(DecodeError::InvalidKey));
+    }
+
+    #[test]
+    fn merge() {
+        let mut registry = vec![
+            Device {
+                mac: "A4:C1:38:4E:2D:5C".to_string(),
+                key: "000102030405060708090a0b0c0d0e0f".to_string(),
+                room: "Living room".to_string(),
+                model: None,
+            },
+            Device {
+                mac: "A4-C1-38-D7-70-32".to_string(),
+                key: "ffeeddccbbaa99887766554433221100".to_string(),
+                room: String::new(),
+                model: Some("miaomiaoce.sensor_ht.t2".to_string()),
+            },
+        ];
+
+        let summary = merge_devices(&mut registry, &parse_extractor_output(OUTPUT));
+
+        assert_eq!(summary.added, Vec::<String>::new());
+        assert_eq!(
+            summary.updated,
+            vec![
+                "A4:C1:38:4E:2D:5C".to_string(),
+                "A4-C1-38-D7-70-32".to_string()
+            ]
+        );
+        assert_eq!(
+            summary.skipped,
+            vec![("Chambre".to_string(), DecodeError::InvalidKey)]
+        );
+
+        // new key, room kept
+        assert_eq!(registry[0].key, "00112233445566778899aabbccddeeff");
+        assert_eq!(registry[0].room, "Living room");
+        // name used as missing room
+        assert_eq!(registry[1].room, "Bébé");
+
+        let summary = merge_devices(&mut registry, &parse_extractor_output(OUTPUT));
+        assert_eq!(summary.unchanged.len(), 2);
+
+        let mut empty = Vec::new();
+        let summary = merge_devices(&mut empty, &parse_extractor_output(OUTPUT));
+        assert_eq!(summary.added.len(), 2);
+        assert_eq!(empty[1].room, "Bébé");
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(text in "(.{0,20}(NAME|MAC|BLE KEY|MODEL|---)?:?.{0,40}\n){0,8}") {
+            let mut registry = Vec::new();
+            let _ = merge_devices(&mut registry, &parse_extractor_output(&text));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Inkbird IBS-TH1 and IBS-TH2 advertisements (local name `sps` or `tps`, manufacturer data)
+//!
+//! These sensors have no company id, the temperature takes its place:
+//!
+//! ```text
+//! temp i16 LE 0.01°C (company id) | humi u16 LE 0.01% | external probe u8
+//!   | CRC-16/MODBUS u16 LE of the 5 previous bytes | batt u8 % | sensor type u8
+//! ```
+//!
+//! Probes without humidity sensor send a humidity of 0.
+
+use crate::{BatteryPercent, DecodeError, Measurement, RelativeHumidity, Temperature};
+
+/// Local names of the IBS-TH1 and IBS-TH2
+pub const INKBIRD_LOCAL_NAMES: [&str; 2] = ["sps", "tps"];
+/// Manufacturer data length, without the company id
+pub const INKBIRD_DATA_LEN: usize = 7;
+
+/// A decoded IBS-TH1 / IBS-TH2 advertisement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InkbirdFrame {
+    pub temperature: Temperature,
+    /// `None` for probes without humidity sensor
+    pub humidity: Option<RelativeHumidity>,
+    /// The temperature is read by the external probe
+    pub external_probe: bool,
+    pub battery: BatteryPercent,
+}
+
+impl InkbirdFrame {
+    pub fn measurements(&self) -> Vec<Measurement> {
+        let reading = match self.humidity {
+            Some(humidity) => Measurement::TemperatureHumidity {
+                temperature: self.temperature,
+                humidity,
+            },
+            None => Measurement::Temperature(self.temperature),
+        };
+        vec![reading, Measurement::Battery(self.battery)]
+    }
+}
+
+/// CRC-16/MODBUS: reflected polynomial 0xA001, initial value 0xFFFF
+fn crc16_modbus(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u16, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Decode Inkbird manufacturer data, `company_id` being the first two bytes
+pub fn parse_inkbird(company_id: u16, data: &[u8]) -> Result<InkbirdFrame, DecodeError> {
+    let [h0, h1, probe, c0, c1, battery, ..] = *data else {
+        return Err(DecodeError::FrameTooShort);
+    };
+    let [t0, t1] = company_id.to_le_bytes();
+    if crc16_modbus(&[t0, t1, h0, h1, probe]) != u16::from_le_bytes([c0, c1]) {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+
+    let humidity = u16::from_le_bytes([h0, h1]);
+
+    Ok(InkbirdFrame {
+        temperature: Temperature::from_centidegrees(company_id as i16),
+        humidity: (humidity != 0).then(|| RelativeHumidity::from_centipercent(humidity)),
+        external_probe: probe != 0,
+        battery: BatteryPercent::new(battery),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc16_modbus(b"123456789"), 0x4B37);
+    }
+
+    #[test]
+    fn frames() {
+        let frame = parse_inkbird(0x0929, &decode_hex("5A140055495508").unwrap()).unwrap();
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(235),
+                    humidity: RelativeHumidity::from_decipercent(521),
+                },
+                Measurement::Battery(BatteryPercent::new(85)),
+            ]
+        );
+        assert!(!frame.external_probe);
+
+        let probe = parse_inkbird(0xFE0C, &decode_hex("8826015E635508").unwrap()).unwrap();
+        assert_eq!(probe.temperature, Temperature::from_decidegrees(-50));
+        assert_eq!(
+            probe.humidity,
+            Some(RelativeHumidity::from_decipercent(986))
+        );
+        assert!(probe.external_probe);
+
+        // IBS-TH2 temperature only probe, humidity 0
+        let th2 = parse_inkbird(0x0866, &decode_hex("0000002E685A08").unwrap()).unwrap();
+        assert_eq!(th2.humidity, None);
+        assert_eq!(
+            th2.measurements(),
+            vec![
+                Measurement::Temperature(Temperature::from_decidegrees(215)),
+                Measurement::Battery(BatteryPercent::new(90)),
+            ]
+        );
+
+        assert_eq!(
+            parse_inkbird(0x092A, &decode_hex("5A140055495508").unwrap()),
+            Err(DecodeError::ChecksumMismatch)
+        );
+        assert_eq!(
+            parse_inkbird(0x0929, &decode_hex("5A1400").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(company_id in any::<u16>(), data in vec(any::<u8>(), 0..10)) {
+            let _ = parse_inkbird(company_id, &data);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Bind keys parsed once, keeping the expanded AES key schedule
+//!
+//! `Ccm` only wraps its block cipher, so building one from a cached schedule is a copy: no hex
+//! parsing nor key expansion per frame.
+
+use aes::Aes128;
+use ccm::{
+    aead::{
+        generic_array::{ArrayLength, GenericArray},
+        KeyInit,
+    },
+    consts::U4,
+    Ccm, NonceSize,
+};
+use std::fmt;
+
+use crate::{decode_hex, DecodeError};
+
+/// Bytes inserted in the middle of a 12 bytes legacy bind key to get the AES key
+pub(crate) const LEGACY_KEY_PADDING: [u8; 4] = [0x8D, 0x3D, 0x3C, 0x97];
+
+/// A device bind key ready to decrypt frames
+#[derive(Clone)]
+pub struct DeviceKey {
+    cipher: Aes128,
+    legacy: bool,
+}
+
+impl DeviceKey {
+    /// 16 bytes AES key, or 12 bytes legacy (MiBeacon v2/v3) bind key
+    pub fn new(key: &[u8]) -> Result<Self, DecodeError> {
+        let (key, legacy) = match key.len() {
+            16 => (key.to_vec(), false),
+            12 => ([&key[..6], &LEGACY_KEY_PADDING, &key[6..]].concat(), true),
+            _ => return Err(DecodeError::InvalidKey),
+        };
+
+        Ok(DeviceKey {
+            cipher: Aes128::new(GenericArray::from_slice(&key)),
+            legacy,
+        })
+    }
+
+    pub fn from_hex(key: &str) -> Result<Self, DecodeError> {
+        Self::new(&decode_hex(key).map_err(|_| DecodeError::InvalidKey)?)
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    /// AES-CCM with a 4 bytes tag and a `N` bytes nonce, legacy keys have none
+    pub(crate) fn ccm<N>(&self) -> Result<Ccm<Aes128, U4, N>, DecodeError>
+    where
+        N: ArrayLength<u8> + NonceSize,
+    {
+        if self.legacy {
+            return Err(DecodeError::InvalidKey);
+        }
+        Ok(Ccm::from(self.cipher.clone()))
+    }
+
+    /// Block cipher of the expanded legacy key
+    pub(crate) fn legacy_cipher(&self) -> Result<&Aes128, DecodeError> {
+        if !self.legacy {
+            return Err(DecodeError::InvalidKey);
+        }
+        Ok(&self.cipher)
+    }
+}
+
+impl fmt::Debug for DeviceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // keep the key out of logs
+        f.debug_struct("DeviceKey")
+            .field("legacy", &self.legacy)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_lengths() {
+        assert!(!DeviceKey::from_hex("000102030405060708090a0b0c0d0e0f")
+            .unwrap()
+            .is_legacy());
+        assert!(DeviceKey::from_hex("000102030405060708090a0b")
+            .unwrap()
+            .is_legacy());
+
+        assert_eq!(
+            DeviceKey::from_hex("0001").unwrap_err(),
+            DecodeError::InvalidKey
+        );
+        assert_eq!(
+            DeviceKey::from_hex("not hex").unwrap_err(),
+            DecodeError::InvalidKey
+        );
+    }
+}
@@ -1,18 +1,76 @@
 use aes::Aes128;
 use ccm::{
-    aead::{generic_array::GenericArray, Aead, KeyInit, Payload},
-    consts::{U12, U4},
+    consts::{U12, U13, U4},
     Ccm,
 };
-use serde::Deserialize;
-use std::{collections::HashMap, num::ParseIntError};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+pub mod ad;
+pub mod atc;
+pub mod bthome;
+pub mod capture;
+pub mod decoder;
+mod device;
+pub mod encoder;
+mod error;
+pub mod govee;
+pub mod import;
+pub mod inkbird;
+mod key;
+mod measurement;
+pub mod mibeacon;
+mod products;
+pub mod qingping;
+pub mod ruuvi;
+mod state;
+pub mod switchbot;
+mod tracker;
+mod units;
+
+pub use ad::{
+    find_local_name, find_manufacturer_data, find_service_data, parse_ad, AdRecord, AdStructures,
+};
+pub use atc::{decode_atc, decrypt_atc, decrypt_atc_with, AtcFormat, AtcFrame};
+pub use bthome::{
+    decrypt_bthome, decrypt_bthome_with, parse_bthome, BtHomeFrame, BtHomeObject, BtHomeValue,
+};
+pub use capture::{is_capture, read_capture, Advertisement, CaptureError};
+pub use decoder::{AdvertisementDecoder, DecoderRegistry};
+pub use device::{format_devices, format_mac, load_devices, parse_mac, save_devices, Device};
+pub use encoder::{encode_frame, encode_object};
+pub use error::{DecodeError, HexError};
+pub use govee::{parse_govee, GoveeFrame, GoveeModel};
+pub use inkbird::{parse_inkbird, InkbirdFrame};
+pub use key::DeviceKey;
+pub use measurement::{ButtonPress, DoorState, Event, Measurement};
+pub use mibeacon::{decode_object, parse_mibeacon, DecodedFrame, FrameControl, MiBeaconFrame};
+pub use products::{product, Product, PRODUCTS};
+pub use qingping::{parse_qingping, QingpingFrame};
+pub use ruuvi::{parse_ruuvi, RuuviFrame};
+pub use state::{Comfort, SensorState, Snapshot, Timestamped};
+pub use switchbot::{parse_switchbot, SwitchBotFrame, SwitchBotModel};
+pub use tracker::{FrameStatus, FrameTracker, REPLAY_WINDOW, RESET_CONFIRMATION};
+pub use units::{BatteryPercent, RelativeHumidity, Temperature, Voltage};
 
 pub type Aes128Ccm = Ccm<Aes128, U4, U12>;
+/// Same cipher with the 13 bytes nonce used by BTHome
+pub type Aes128Ccm13 = Ccm<Aes128, U4, U13>;
 
-pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
-    (0..s.len())
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, HexError> {
+    let digits = s.as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return Err(HexError::OddLength);
+    }
+
+    let digit = |offset: usize| {
+        (digits[offset] as char)
+            .to_digit(16)
+            .map(|digit| digit as u8)
+            .ok_or(HexError::InvalidDigit(offset))
+    };
+    (0..digits.len())
         .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .map(|offset| Ok((digit(offset)? << 4) | digit(offset + 1)?))
         .collect()
 }
 
@@ -24,112 +82,187 @@ pub fn encode_hex(bytes: &[u8]) -> String {
         .join(" ")
 }
 
-#[derive(Debug, Deserialize, Clone)]
-struct Device {
-    pub mac: String,
-    pub key: String,
-    pub room: String,
-}
-
+#[cfg(feature = "embedded-devices")]
 static DEVICES_JSON: &str = include_str!("devices.json");
 
+/// Device registry decoding the frames of the registered sensors
+///
+/// Devices are looked up by binary MAC and their keys parsed once on registration. The registry
+/// is shared copy-on-write: cloning a `Decryptor` to hand it to another thread is cheap.
+#[derive(Debug, Clone, Default)]
 pub struct Decryptor {
-    devices: HashMap<String, Device>,
+    devices: Arc<HashMap<[u8; 6], Registered>>,
+}
+
+#[derive(Debug, Clone)]
+struct Registered {
+    device: Device,
+    key: DeviceKey,
 }
 
 impl Decryptor {
-    pub fn new() -> Self {
-        let devices_list: Vec<Device> = serde_json::from_str(DEVICES_JSON).unwrap();
+    /// An empty registry, see `add_device`
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// Registry from the `devices.json` embedded at build time
+    #[cfg(feature = "embedded-devices")]
+    pub fn from_embedded() -> anyhow::Result<Self> {
+        Self::from_json_str(DEVICES_JSON)
+    }
 
-        let devices: HashMap<String, Device> =
-            HashMap::from_iter(devices_list.iter().map(|d| (d.mac.clone(), d.clone())));
+    /// Registry from a JSON list of devices
+    pub fn from_json_str(s: &str) -> anyhow::Result<Self> {
+        Ok(Self::from_devices(device::parse_json(s)?)?)
+    }
 
-        Decryptor { devices }
+    /// Registry from a TOML file made of `[[devices]]` tables
+    pub fn from_toml_str(s: &str) -> anyhow::Result<Self> {
+        Ok(Self::from_devices(device::parse_toml(s)?)?)
     }
 
-    pub fn decode_frame_data(&self, data: &[u8]) -> Option<u16> {
-        if data.len() < 26 {
-            return None;
+    /// Registry from a `.toml` or `.json` file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Ok(Self::from_devices(device::load_devices(path)?)?)
+    }
+
+    pub fn from_devices<I: IntoIterator<Item = Device>>(devices: I) -> Result<Self, DecodeError> {
+        let mut decryptor = Self::empty();
+        for device in devices {
+            decryptor.add_device(device)?;
         }
+        Ok(decryptor)
+    }
 
-        let mac_string = format!(
-            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
-            data[17], data[16], data[15], data[14], data[13], data[12]
-        );
-
-        if let Some(device) = &self.devices.get(&mac_string) {
-            let nonce: [u8; 12] = [
-                data[12], data[13], data[14], data[15], data[16], data[17], // device mac
-                data[9], data[10], // device type
-                data[11], // frame cnt
-                data[23], data[24], data[25], // ext.cnt
-            ];
-
-            let nonce: &GenericArray<u8, U12> = GenericArray::from_slice(&nonce);
-
-            let key = decode_hex(&device.key).expect("Unable to decode key");
-
-            let cipher = Aes128Ccm::new_from_slice(&key).unwrap();
-
-            let encrypted_data: &[u8] = &data[18..23];
-            let tag = &data[26..];
-
-            let to_decrypt = [encrypted_data, tag].concat();
-
-            let payload = Payload {
-                msg: &to_decrypt,
-                aad: &[0x11],
-            };
-
-            // println!("Nonce: {:?} ({:?})", encode_hex(&nonce), nonce.len());
-
-            // println!(
-            //     "To decrypt: {:?} ({:?})",
-            //     encode_hex(&to_decrypt),
-            //     to_decrypt.len()
-            // );
-
-            let plain_data = cipher.decrypt(nonce, payload);
-
-            if let Ok(plain_data) = plain_data {
-                if plain_data[0] == 4 {
-                    assert!(((0xFF as u16) << 8) + 0xFF as u16 == 0xFFFF);
-
-                    let temp: u16 = ((plain_data[4] as u16) << 8) + plain_data[3] as u16;
-                    // println!("Je renvoie {:?}", temp);
-                    return Some(temp);
-                }
-
-                // println!(
-                //     "Decrypted: {:?} {:?} : {:?}",
-                //     encode_hex(&plain_data),
-                //     plain_data,
-                //     if plain_data[0] == 4 {
-                //         "TEMP"
-                //     } else {
-                //         "HUMIDITY"
-                //     },
-                // );
-
-                // println!(
-                //     "{:?} {:?} {:?} {:?}",
-                //     (plain_data[1] as f32 * 16.0 + plain_data[0] as f32) / 10.0,
-                //     (plain_data[2] as f32 * 16.0 + plain_data[1] as f32) / 10.0,
-                //     (plain_data[3] as f32 * 16.0 + plain_data[2] as f32) / 10.0,
-                //     (plain_data[4] as f32 * 16.0 + plain_data[3] as f32) / 10.0,
-                // );
-                // println!(
-                //     "{:?} {:?} {:?} {:?}",
-                //     (plain_data[0] as f32 * 16.0 + plain_data[1] as f32) / 10.0,
-                //     (plain_data[1] as f32 * 16.0 + plain_data[2] as f32) / 10.0,
-                //     (plain_data[2] as f32 * 16.0 + plain_data[3] as f32) / 10.0,
-                //     (plain_data[3] as f32 * 16.0 + plain_data[4] as f32) / 10.0,
-                // );
-            }
+    /// Register a device, returning the previous entry for the same MAC
+    ///
+    /// Fails with `InvalidMac` / `InvalidKey` when the entry cannot be parsed.
+    pub fn add_device(&mut self, mut device: Device) -> Result<Option<Device>, DecodeError> {
+        let mac = device::parse_mac(&device.mac).ok_or(DecodeError::InvalidMac)?;
+        let key = DeviceKey::from_hex(&device.key)?;
+        device.mac = device::normalize_mac(&device.mac);
+
+        let previous = Arc::make_mut(&mut self.devices).insert(mac, Registered { device, key });
+        Ok(previous.map(|registered| registered.device))
+    }
+
+    pub fn remove_device(&mut self, mac: &str) -> Option<Device> {
+        let mac = device::parse_mac(mac)?;
+        Arc::make_mut(&mut self.devices)
+            .remove(&mac)
+            .map(|registered| registered.device)
+    }
+
+    /// Registered device of a MAC in display order
+    pub fn device(&self, mac: [u8; 6]) -> Option<&Device> {
+        self.devices.get(&mac).map(|registered| &registered.device)
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = &Device> {
+        self.devices.values().map(|registered| &registered.device)
+    }
+
+    /// Decode MiBeacon advertisements, encrypted frames need the MAC included
+    pub fn decode_frame_data(&self, data: &[u8]) -> Result<Measurement, DecodeError> {
+        Ok(self.decode_frame(None, data)?.measurement)
+    }
+
+    /// Decode MiBeacon advertisements with `addr`, the advertiser address in display order, as
+    /// device MAC for the key lookup and the nonce
+    ///
+    /// Frames without the "MAC included" flag can only be decrypted this way.
+    pub fn decode_with_address(
+        &self,
+        addr: [u8; 6],
+        data: &[u8],
+    ) -> Result<Measurement, DecodeError> {
+        Ok(self.decode_frame(Some(addr), data)?.measurement)
+    }
+
+    /// Decode MiBeacon advertisements along with their product and object type, `addr` being
+    /// used as in `decode_with_address` when given
+    pub fn decode_frame(
+        &self,
+        addr: Option<[u8; 6]>,
+        data: &[u8],
+    ) -> Result<DecodedFrame, DecodeError> {
+        let mut frame = mibeacon_frame(data)?;
+        if addr.is_some() {
+            frame.mac = addr;
         }
 
-        None
+        if !frame.frame_control.object_included() {
+            return Err(DecodeError::NoObject);
+        }
+        let object = if frame.frame_control.is_encrypted() {
+            let mac = frame.mac.ok_or(DecodeError::MissingMac)?;
+            frame.decrypt_with(self.key(mac)?)?
+        } else {
+            frame.object.to_vec()
+        };
+        let measurement = decode_object(&object)?;
+
+        Ok(DecodedFrame {
+            product_id: frame.product_id,
+            product: product(frame.product_id),
+            mac: frame.mac,
+            // decode_object checked the object header
+            object_id: u16::from_le_bytes([object[0], object[1]]),
+            measurement,
+        })
     }
+
+    /// Decode ATC1441 / pvvx custom firmware advertisements, `mac` being the advertiser address
+    /// used to look up the key of encrypted frames
+    pub fn decode_atc_frame(
+        &self,
+        mac: [u8; 6],
+        data: &[u8],
+    ) -> Result<Vec<Measurement>, DecodeError> {
+        let service_data =
+            find_service_data(data, atc::ATC_SERVICE_UUID).ok_or(DecodeError::NoServiceData)?;
+        let format = AtcFormat::from_len(service_data.len()).ok_or(DecodeError::UnknownFormat)?;
+
+        let frame = if format.is_encrypted() {
+            decrypt_atc_with(service_data, mac, self.key(mac)?)?
+        } else {
+            decode_atc(service_data)?
+        };
+
+        Ok(frame.measurements)
+    }
+
+    /// Decode BTHome v2 advertisements, `mac` being the advertiser address used to look up
+    /// the key of encrypted frames
+    pub fn decode_bthome_frame(
+        &self,
+        mac: [u8; 6],
+        data: &[u8],
+    ) -> Result<Vec<Measurement>, DecodeError> {
+        let service_data = find_service_data(data, bthome::BTHOME_SERVICE_UUID)
+            .ok_or(DecodeError::NoServiceData)?;
+
+        let frame = match parse_bthome(service_data) {
+            Err(DecodeError::Encrypted) => decrypt_bthome_with(service_data, mac, self.key(mac)?)?,
+            result => result?,
+        };
+
+        Ok(frame.measurements())
+    }
+
+    fn key(&self, mac: [u8; 6]) -> Result<&DeviceKey, DecodeError> {
+        self.devices
+            .get(&mac)
+            .map(|registered| &registered.key)
+            .ok_or(DecodeError::UnknownDevice(mac))
+    }
+}
+
+fn mibeacon_frame(data: &[u8]) -> Result<MiBeaconFrame<'_>, DecodeError> {
+    let service_data = find_service_data(data, mibeacon::MIBEACON_SERVICE_UUID)
+        .ok_or(DecodeError::NoServiceData)?;
+    parse_mibeacon(service_data)
 }
 
 #[cfg(test)]
@@ -167,20 +300,526 @@ mod tests {
             "02 01 06 1A 16 95 FE 58 58 5B 05 4F 5C 2D 4E 38 C1 A4 48 86 C7 D7 A1 00 00 00 7A 54 16 8F", // 23.5°C 60%
         ];
 
-        let decryptor = Decryptor::new();
+        // the device key is not part of the repository: check the frame layout, and that another
+        // key is rejected rather than decoding garbage
+        let decryptor = test_decryptor();
         for frame in frames.iter() {
-            let bytes = frame
-                .split(" ")
-                .map(|x| u8::from_str_radix(x, 16).unwrap())
-                .collect::<Vec<u8>>();
-            let result = decryptor.decode_frame_data(&bytes);
-            dbg!(result);
-            //assert!(result.is_some());
+            let bytes = decode_hex(&frame.replace(' ', "")).unwrap();
+            let parsed = mibeacon_frame(&bytes).unwrap();
+
+            assert!(parsed.frame_control.is_encrypted());
+            assert_eq!(parsed.product_id, 0x055B);
+            assert_eq!(parsed.mac, Some([0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C]));
+            assert_eq!(parsed.object.len(), 5);
+            assert_eq!(parsed.ext_counter, Some([0, 0, 0]));
+            assert_eq!(
+                decryptor.decode_frame_data(&bytes),
+                Err(DecodeError::TagMismatch)
+            );
         }
 
-        let bytes =
-            decode_hex("0201061A1695FE58585B054F5C2D4E38C1A44886C7D7A10000007A54168F").unwrap();
+        // the same headers with objects encrypted under the test key
+        let header = decode_hex("58585B054F5C2D4E38C1A4").unwrap();
+        let bytes = encrypted_advertisement(&header, &[0x04, 0x10, 0x02, 0xEC, 0x00], [0, 0, 0]);
+        assert_eq!(
+            decryptor.decode_frame_data(&bytes),
+            Ok(Measurement::Temperature(Temperature::from_decidegrees(236)))
+        );
 
-        assert_eq!(decryptor.decode_frame_data(&bytes), Some(236_u16));
+        let header = decode_hex("58585B054E5C2D4E38C1A4").unwrap();
+        let bytes = encrypted_advertisement(&header, &[0x06, 0x10, 0x02, 0x80, 0x02], [0, 0, 0]);
+        assert_eq!(
+            decryptor.decode_frame_data(&bytes),
+            Ok(Measurement::Humidity(RelativeHumidity::from_decipercent(
+                640
+            )))
+        );
+    }
+
+    const TEST_MAC: &str = "A4:C1:38:4E:2D:5C";
+    const TEST_KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn test_decryptor() -> Decryptor {
+        Decryptor::from_devices([Device {
+            mac: TEST_MAC.to_string(),
+            key: TEST_KEY.to_string(),
+            room: "Test".to_string(),
+            model: None,
+        }])
+        .unwrap()
+    }
+
+    /// Build an advertisement carrying `header` (frame ctrl up to capability) followed by `plain` encrypted
+    fn encrypted_advertisement(header: &[u8], plain: &[u8], ext_counter: [u8; 3]) -> Vec<u8> {
+        use ccm::aead::{generic_array::GenericArray, Aead, KeyInit, Payload};
+
+        let mac: Vec<u8> = header[5..11].to_vec();
+        let nonce = [&mac[..], &header[2..5], &ext_counter].concat();
+        let cipher = Aes128Ccm::new_from_slice(&decode_hex(TEST_KEY).unwrap()).unwrap();
+        let encrypted = cipher
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: plain,
+                    aad: &[0x11],
+                },
+            )
+            .unwrap();
+        let (object, mic) = encrypted.split_at(plain.len());
+
+        let service_data = [header, object, &ext_counter, mic].concat();
+        let mut advertisement = vec![
+            0x02,
+            0x01,
+            0x06,
+            (service_data.len() + 3) as u8,
+            0x16,
+            0x95,
+            0xFE,
+        ];
+        advertisement.extend_from_slice(&service_data);
+        advertisement
+    }
+
+    #[test]
+    fn decode_with_capability_byte() {
+        let header = decode_hex("78585B05105C2D4E38C1A408").unwrap();
+        let frame = encrypted_advertisement(&header, &[0x04, 0x10, 0x02, 0xEC, 0x00], [1, 0, 0]);
+
+        assert_eq!(
+            test_decryptor().decode_frame_data(&frame),
+            Ok(Measurement::Temperature(Temperature::from_decidegrees(236)))
+        );
+    }
+
+    #[test]
+    fn decode_without_flags() {
+        let header = decode_hex("58585B05115C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &[0x04, 0x10, 0x02, 0xF6, 0xFF], [2, 0, 0]);
+
+        assert_eq!(
+            test_decryptor().decode_frame_data(&frame[3..]),
+            Ok(Measurement::Temperature(Temperature::from_decidegrees(-10)))
+        );
+    }
+
+    #[test]
+    fn decode_without_mac() {
+        let header = decode_hex("58585B05125C2D4E38C1A4").unwrap();
+        let mut frame =
+            encrypted_advertisement(&header, &[0x06, 0x10, 0x02, 0xC2, 0x01], [3, 0, 0]);
+        // same frame, "MAC included" cleared and the MAC dropped
+        frame.drain(12..18);
+        frame[3] -= 6;
+        frame[7] &= !0x10;
+
+        let decryptor = test_decryptor();
+        assert_eq!(
+            decryptor.decode_frame_data(&frame),
+            Err(DecodeError::MissingMac)
+        );
+
+        let addr = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+        assert_eq!(
+            decryptor.decode_with_address(addr, &frame),
+            Ok(Measurement::Humidity(RelativeHumidity::from_decipercent(
+                450
+            )))
+        );
+        assert_eq!(
+            decryptor.decode_with_address([0; 6], &frame),
+            Err(DecodeError::UnknownDevice([0; 6]))
+        );
+    }
+
+    #[test]
+    fn decode_products() {
+        let decryptor = test_decryptor();
+
+        let header = decode_hex("58585B05135C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &[0x04, 0x10, 0x02, 0xEC, 0x00], [4, 0, 0]);
+        let decoded = decryptor.decode_frame(None, &frame).unwrap();
+        assert_eq!(decoded.model(), Some("LYWSD03MMC"));
+        assert_eq!(decoded.object_id, mibeacon::OBJECT_TEMPERATURE);
+        assert_eq!(decoded.mac, Some([0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C]));
+        assert!(!decoded.is_unexpected());
+
+        // a thermometer sending illuminance
+        let frame = encrypted_advertisement(&header, &[0x07, 0x10, 0x03, 0x64, 0, 0], [5, 0, 0]);
+        let decoded = decryptor.decode_frame(None, &frame).unwrap();
+        assert_eq!(decoded.measurement, Measurement::Illuminance(100));
+        assert!(decoded.is_unexpected());
+
+        let header = decode_hex("5858FFFF145C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &[0x07, 0x10, 0x03, 0x64, 0, 0], [6, 0, 0]);
+        let decoded = decryptor.decode_frame(None, &frame).unwrap();
+        assert_eq!(decoded.product_id, 0xFFFF);
+        assert_eq!(decoded.model(), None);
+        assert!(!decoded.is_unexpected());
+    }
+
+    #[test]
+    fn decode_sensor_objects() {
+        // Headers of the captured LYWSD03MMC frames, re-encrypted with the test key
+        let cases = [
+            (
+                "58585B050D5C2D4E38C1A4",
+                "041002EC00",
+                Measurement::Temperature(Temperature::from_decidegrees(236)),
+            ),
+            (
+                "58585B050E5C2D4E38C1A4",
+                "0610023802",
+                Measurement::Humidity(RelativeHumidity::from_decipercent(568)),
+            ),
+            (
+                "58585B050F5C2D4E38C1A4",
+                "0A100150",
+                Measurement::Battery(BatteryPercent::new(80)),
+            ),
+            (
+                "58585B05375C2D4E38C1A4",
+                "0D1004E9003002",
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(233),
+                    humidity: RelativeHumidity::from_decipercent(560),
+                },
+            ),
+        ];
+
+        let decryptor = test_decryptor();
+        for (header, plain, expected) in cases {
+            let frame = encrypted_advertisement(
+                &decode_hex(header).unwrap(),
+                &decode_hex(plain).unwrap(),
+                [0, 0, 0],
+            );
+            assert_eq!(decryptor.decode_frame_data(&frame), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn decode_unencrypted() {
+        // Plain v5 frame from a device missing in the registry
+        let frame = decode_hex("020106121695FE50505B050D1122334455660A100150").unwrap();
+
+        assert_eq!(
+            test_decryptor().decode_frame_data(&frame),
+            Ok(Measurement::Battery(BatteryPercent::new(80)))
+        );
+    }
+
+    #[test]
+    fn decode_legacy_key_length() {
+        // v3 frame: a 16 bytes key registered for a legacy device is rejected
+        let frame = decode_hex("020106171695FE58305B050D5C2D4E38C1A4A1B2C3D4E5010203FF").unwrap();
+
+        assert_eq!(
+            test_decryptor().decode_frame_data(&frame),
+            Err(DecodeError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn decode_wrong_key() {
+        let header = decode_hex("58585B054F5C2D4E38C1A4").unwrap();
+        let mut frame =
+            encrypted_advertisement(&header, &decode_hex("041002EC00").unwrap(), [0; 3]);
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+
+        assert_eq!(
+            test_decryptor().decode_frame_data(&frame),
+            Err(DecodeError::TagMismatch)
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        let decryptor = test_decryptor();
+
+        assert_eq!(
+            decryptor.decode_frame_data(&decode_hex("020106").unwrap()),
+            Err(DecodeError::NoServiceData)
+        );
+        assert_eq!(
+            decryptor.decode_frame_data(&decode_hex("0201060A1695FE58585B050D5C2D").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            decryptor
+                .decode_frame_data(&decode_hex("0201060E1695FE10505B050D5C2D4E38C1A4").unwrap()),
+            Err(DecodeError::NoObject)
+        );
+
+        let header = decode_hex("58585B050D112233445566").unwrap();
+        let frame = encrypted_advertisement(&header, &decode_hex("041002EC00").unwrap(), [0; 3]);
+        assert_eq!(
+            decryptor.decode_frame_data(&frame),
+            Err(DecodeError::UnknownDevice([
+                0x66, 0x55, 0x44, 0x33, 0x22, 0x11
+            ]))
+        );
+
+        let header = decode_hex("58585B050D5C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &decode_hex("FF0F0100").unwrap(), [0; 3]);
+        assert_eq!(
+            decryptor.decode_frame_data(&frame),
+            Err(DecodeError::UnsupportedObject(0x0FFF))
+        );
+
+        // entries are checked on registration
+        let mut decryptor = decryptor;
+        let mut device = decryptor.devices().next().unwrap().clone();
+        device.key = "not hex".to_string();
+        assert_eq!(
+            decryptor.add_device(device.clone()),
+            Err(DecodeError::InvalidKey)
+        );
+        device.mac = "A4:C1:38".to_string();
+        assert_eq!(
+            decryptor.add_device(device.clone()),
+            Err(DecodeError::InvalidMac)
+        );
+        device.mac = "+4:C1:38:4E:2D:5C".to_string();
+        assert_eq!(decryptor.add_device(device), Err(DecodeError::InvalidMac));
+        assert!(Decryptor::from_json_str(r#"[{"mac": "?", "key": "", "room": ""}]"#).is_err());
+    }
+
+    #[test]
+    fn registry_formats() {
+        let json = r#"[{"mac": "a4:c1:38:4e:2d:5c", "key": "000102030405060708090a0b0c0d0e0f", "room": "Salon"}]"#;
+        let toml = r#"
+            [[devices]]
+            mac = "A4:C1:38:4E:2D:5C"
+            key = "000102030405060708090a0b0c0d0e0f"
+            room = "Salon"
+        "#;
+
+        let from_json = Decryptor::from_json_str(json).unwrap();
+        let from_toml = Decryptor::from_toml_str(toml).unwrap();
+
+        assert_eq!(
+            from_json.devices().collect::<Vec<_>>(),
+            from_toml.devices().collect::<Vec<_>>()
+        );
+        assert_eq!(from_json.devices().next().unwrap().mac, TEST_MAC);
+
+        assert!(Decryptor::from_json_str("{}").is_err());
+        assert!(Decryptor::from_toml_str("devices = 1").is_err());
+    }
+
+    #[test]
+    fn registry_files() {
+        let devices = vec![Device {
+            mac: TEST_MAC.to_string(),
+            key: TEST_KEY.to_string(),
+            room: "Salon".to_string(),
+            model: Some("miaomiaoce.sensor_ht.t2".to_string()),
+        }];
+        let dir = std::env::temp_dir();
+
+        for name in ["ble_decode_registry.json", "ble_decode_registry.toml"] {
+            let path = dir.join(format!("{}-{}", std::process::id(), name));
+            save_devices(&path, &devices).unwrap();
+
+            assert_eq!(load_devices(&path).unwrap(), devices);
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                format_devices(&path, &devices).unwrap()
+            );
+            assert_eq!(Decryptor::from_path(&path).unwrap().devices().count(), 1);
+            std::fs::remove_file(path).unwrap();
+        }
+
+        // room and model are optional
+        let decryptor = Decryptor::from_json_str(
+            r#"[{"mac": "A4:C1:38:4E:2D:5C", "key": "000102030405060708090a0b0c0d0e0f"}]"#,
+        )
+        .unwrap();
+        assert_eq!(decryptor.devices().next().unwrap().room, "");
+
+        let toml = format_devices("devices.toml", &devices).unwrap();
+        assert!(toml.starts_with("[[devices]]"));
+        assert_eq!(
+            Decryptor::from_toml_str(&toml).unwrap().devices().count(),
+            1
+        );
+        assert!(format_devices("devices.json", &devices)
+            .unwrap()
+            .starts_with('['));
+    }
+
+    #[test]
+    fn registry_updates() {
+        let header = decode_hex("58585B054F5C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &decode_hex("041002EC00").unwrap(), [0; 3]);
+
+        let mut decryptor = Decryptor::empty();
+        assert!(decryptor.decode_frame_data(&frame).is_err());
+
+        let device = test_decryptor().devices().next().unwrap().clone();
+        assert_eq!(decryptor.add_device(device.clone()), Ok(None));
+        assert_eq!(
+            decryptor.decode_frame_data(&frame),
+            Ok(Measurement::Temperature(Temperature::from_decidegrees(236)))
+        );
+
+        let shared = decryptor.clone();
+        assert_eq!(
+            shared.device([0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C]),
+            Some(&device)
+        );
+
+        assert_eq!(decryptor.remove_device("a4:c1:38:4e:2d:5c"), Some(device));
+        assert_eq!(decryptor.devices().count(), 0);
+        // the clone kept its own copy of the registry
+        assert_eq!(shared.devices().count(), 1);
+        assert!(shared.decode_frame_data(&frame).is_ok());
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let header = decode_hex("58585B054F5C2D4E38C1A4").unwrap();
+        let frame = encrypted_advertisement(&header, &decode_hex("041002EC00").unwrap(), [0; 3]);
+        let decryptor = test_decryptor();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    assert_eq!(
+                        decryptor.decode_frame_data(&frame),
+                        Ok(Measurement::Temperature(Temperature::from_decidegrees(236)))
+                    );
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn decode_atc_advertisements() {
+        let decryptor = test_decryptor();
+        let mac = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+        let plain = decode_hex("02010610161A18a4c1384e2d5c00a22f5f0bf819").unwrap();
+        assert_eq!(decryptor.decode_atc_frame(mac, &plain).unwrap().len(), 3);
+
+        let service_data =
+            atc::encrypt_atc(&[0x45, 0x78, 0x64], 1, mac, &decode_hex(TEST_KEY).unwrap());
+        let mut encrypted = vec![service_data.len() as u8 + 3, 0x16, 0x1A, 0x18];
+        encrypted.extend_from_slice(&service_data);
+
+        assert_eq!(
+            decryptor.decode_atc_frame(mac, &encrypted),
+            Ok(vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(-55),
+                    humidity: RelativeHumidity::from_decipercent(600),
+                },
+                Measurement::Battery(BatteryPercent::new(100)),
+            ])
+        );
+        assert_eq!(
+            decryptor.decode_atc_frame([0; 6], &encrypted),
+            Err(DecodeError::UnknownDevice([0; 6]))
+        );
+    }
+
+    #[test]
+    fn decode_bthome_advertisements() {
+        let decryptor = test_decryptor();
+        let mac = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+        let plain = decode_hex("0201060A16D2FC4002CA0903BF13").unwrap();
+        assert_eq!(
+            decryptor.decode_bthome_frame([0; 6], &plain).unwrap().len(),
+            2
+        );
+
+        let service_data = bthome::encrypt_bthome(
+            &decode_hex("0164").unwrap(),
+            1,
+            mac,
+            &decode_hex(TEST_KEY).unwrap(),
+        );
+        let mut encrypted = vec![service_data.len() as u8 + 3, 0x16, 0xD2, 0xFC];
+        encrypted.extend_from_slice(&service_data);
+
+        assert_eq!(
+            decryptor.decode_bthome_frame(mac, &encrypted),
+            Ok(vec![Measurement::Battery(BatteryPercent::new(100))])
+        );
+        assert_eq!(
+            decryptor.decode_bthome_frame([0; 6], &encrypted),
+            Err(DecodeError::UnknownDevice([0; 6]))
+        );
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(decode_hex("0aFf"), Ok(vec![0x0A, 0xFF]));
+        assert_eq!(decode_hex(""), Ok(vec![]));
+        assert_eq!(decode_hex("020"), Err(HexError::OddLength));
+        assert_eq!(decode_hex("0g"), Err(HexError::InvalidDigit(1)));
+        assert_eq!(decode_hex("+f"), Err(HexError::InvalidDigit(0)));
+        // 2 bytes char, sliced in the middle before
+        assert_eq!(decode_hex("é"), Err(HexError::InvalidDigit(0)));
+    }
+
+    /// Service data in an advertisement, after the flags
+    fn advertisement(uuid: u16, service_data: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x02, 0x01, 0x06, service_data.len() as u8 + 3, 0x16];
+        data.extend_from_slice(&uuid.to_le_bytes());
+        data.extend_from_slice(service_data);
+        data
+    }
+
+    mod total {
+        use super::*;
+        use proptest::{collection::vec, prelude::*};
+
+        const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+        proptest! {
+            #[test]
+            fn decode_hex_never_panics(s in ".*") {
+                let _ = decode_hex(&s);
+            }
+
+            #[test]
+            fn hex_round_trip(bytes in vec(any::<u8>(), 0..64)) {
+                prop_assert_eq!(decode_hex(&encode_hex(&bytes).replace(' ', "")), Ok(bytes));
+            }
+
+            #[test]
+            fn decoders_never_panic(data in vec(any::<u8>(), 0..64), mac in any::<[u8; 6]>()) {
+                let decryptor = test_decryptor();
+
+                let _ = decryptor.decode_frame_data(&data);
+                let _ = decryptor.decode_with_address(mac, &data);
+                let _ = decryptor.decode_atc_frame(mac, &data);
+                let _ = decryptor.decode_bthome_frame(mac, &data);
+                let _ = DecoderRegistry::with_defaults(&decryptor).decode_advertisement(None, &data);
+            }
+
+            /// Frames of the registered device, to go down to decryption and object parsing
+            #[test]
+            fn registered_frames_never_panic(
+                frame_control in any::<u16>(),
+                rest in vec(any::<u8>(), 0..40),
+            ) {
+                let decryptor = test_decryptor();
+
+                let mut mibeacon = frame_control.to_le_bytes().to_vec();
+                mibeacon.extend_from_slice(&[0x5B, 0x05, 0x4F]);
+                mibeacon.extend(MAC.iter().rev());
+                mibeacon.extend_from_slice(&rest);
+                let _ = decryptor.decode_frame_data(&advertisement(0xFE95, &mibeacon));
+                let _ = decryptor.decode_with_address(MAC, &advertisement(0xFE95, &rest));
+
+                let _ = decryptor.decode_atc_frame(MAC, &advertisement(0x181A, &rest));
+                let _ = decryptor.decode_bthome_frame(MAC, &advertisement(0xFCD2, &rest));
+            }
+        }
     }
 }
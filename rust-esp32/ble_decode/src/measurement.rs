@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{BatteryPercent, RelativeHumidity, Temperature, Voltage};
+
+/// A sensor reading, whatever the advertisement format
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Measurement {
+    Temperature(Temperature),
+    Humidity(RelativeHumidity),
+    TemperatureHumidity {
+        temperature: Temperature,
+        humidity: RelativeHumidity,
+    },
+    Battery(BatteryPercent),
+    /// Battery voltage
+    Voltage(Voltage),
+    /// lux
+    Illuminance(u32),
+    /// Pa
+    Pressure(u32),
+    /// ppm
+    Co2(u16),
+    /// µg/m³
+    Pm25(u16),
+    /// µg/m³
+    Pm10(u16),
+    /// Soil moisture, %
+    Moisture(u8),
+    /// Soil conductivity, µS/cm
+    Conductivity(u16),
+    /// Hundredths of mg/m³
+    Formaldehyde(u16),
+    /// Sent when something happens rather than periodically
+    Event(Event),
+}
+
+/// State change reported by buttons, contact and motion sensors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Button {
+        index: u16,
+        press: ButtonPress,
+    },
+    /// Motion detected, with the illuminance in lux
+    Motion {
+        illuminance: u32,
+    },
+    /// No motion for `seconds`, sent after the motion timeout
+    NoMotion {
+        seconds: u32,
+    },
+    Door(DoorState),
+    /// Light detected, `false` when dark
+    Light(bool),
+    /// Water leak sensor, `true` when wet
+    WaterLeak(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonPress {
+    Single,
+    Double,
+    Long,
+    Triple,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoorState {
+    Open,
+    Closed,
+    /// Still open after the sensor timeout
+    LeftOpen,
+    /// The sensor restarted
+    Reset,
+}
+
+impl Measurement {
+    /// Temperature, if the measurement carries one
+    pub fn temperature(&self) -> Option<Temperature> {
+        match self {
+            Measurement::Temperature(t) => Some(*t),
+            Measurement::TemperatureHumidity { temperature, .. } => Some(*temperature),
+            _ => None,
+        }
+    }
+
+    /// Event, for measurements sent when something happens
+    pub fn event(&self) -> Option<Event> {
+        match self {
+            Measurement::Event(event) => Some(*event),
+            _ => None,
+        }
+    }
+
+    /// Relative humidity, if the measurement carries one
+    pub fn humidity(&self) -> Option<RelativeHumidity> {
+        match self {
+            Measurement::Humidity(h) => Some(*h),
+            Measurement::TemperatureHumidity { humidity, .. } => Some(*humidity),
+            _ => None,
+        }
+    }
+}
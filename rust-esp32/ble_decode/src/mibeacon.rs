@@ -0,0 +1,645 @@
+//! MiBeacon service data (UUID 0xFE95) parsing
+//!
+//! Layout of the service data, all multi-byte fields are little-endian:
+//!
+//! ```text
+//! Frame ctrl | Product id | Frame cnt | [MAC] | [Capability] | [IO cap.] | [Object] | [Ext.cnt | MIC]
+//!    2       |     2      |     1     |   6   |      1       |     2     |    n     |    3     |  4
+//! ```
+//!
+//! Legacy encrypted frames (version 2 and 3) end with the 3 bytes ext. counter and a single
+//! extra byte instead of the MIC, and use a 12 bytes bind key.
+
+use aes::{cipher::BlockEncrypt, Aes128};
+use ccm::aead::{generic_array::GenericArray, Aead, Payload};
+
+use crate::{
+    Aes128Ccm, BatteryPercent, ButtonPress, DecodeError, DeviceKey, DoorState, Event, Measurement,
+    Product, RelativeHumidity, Temperature,
+};
+
+/// 0xFE95 Xiaomi Inc.
+pub const MIBEACON_SERVICE_UUID: u16 = 0xFE95;
+
+const FRAME_CONTROL_ENCRYPTED: u16 = 1 << 3;
+const FRAME_CONTROL_MAC_INCLUDED: u16 = 1 << 4;
+const FRAME_CONTROL_CAPABILITY_INCLUDED: u16 = 1 << 5;
+const FRAME_CONTROL_OBJECT_INCLUDED: u16 = 1 << 6;
+const FRAME_CONTROL_MESH: u16 = 1 << 7;
+const FRAME_CONTROL_REGISTERED: u16 = 1 << 8;
+const FRAME_CONTROL_SOLICITED: u16 = 1 << 9;
+
+const CAPABILITY_IO: u8 = 1 << 5;
+
+pub const OBJECT_MOTION_ILLUMINANCE: u16 = 0x000F;
+pub const OBJECT_BUTTON: u16 = 0x1001;
+pub const OBJECT_TEMPERATURE: u16 = 0x1004;
+pub const OBJECT_HUMIDITY: u16 = 0x1006;
+pub const OBJECT_ILLUMINANCE: u16 = 0x1007;
+pub const OBJECT_MOISTURE: u16 = 0x1008;
+pub const OBJECT_CONDUCTIVITY: u16 = 0x1009;
+pub const OBJECT_BATTERY: u16 = 0x100A;
+pub const OBJECT_TEMPERATURE_HUMIDITY: u16 = 0x100D;
+pub const OBJECT_FORMALDEHYDE: u16 = 0x1010;
+pub const OBJECT_WATER_LEAK: u16 = 0x1014;
+pub const OBJECT_NO_MOTION: u16 = 0x1017;
+pub const OBJECT_LIGHT: u16 = 0x1018;
+pub const OBJECT_DOOR: u16 = 0x1019;
+pub const OBJECT_BATTERY_V2: u16 = 0x4803;
+pub const OBJECT_TEMPERATURE_V2: u16 = 0x4C01;
+pub const OBJECT_HUMIDITY_V2: u16 = 0x4C02;
+
+/// Length of the extended frame counter appended to encrypted frames
+pub const EXT_COUNTER_LEN: usize = 3;
+/// Length of the AES-CCM tag appended to encrypted frames
+pub const MIC_LEN: usize = 4;
+/// Length of the byte following the ext. counter in legacy encrypted frames
+const LEGACY_TRAILER_LEN: usize = 1;
+
+/// The 16 bits "frame control" field heading every MiBeacon frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameControl(pub u16);
+
+impl FrameControl {
+    pub fn is_encrypted(&self) -> bool {
+        self.0 & FRAME_CONTROL_ENCRYPTED != 0
+    }
+
+    pub fn mac_included(&self) -> bool {
+        self.0 & FRAME_CONTROL_MAC_INCLUDED != 0
+    }
+
+    pub fn capability_included(&self) -> bool {
+        self.0 & FRAME_CONTROL_CAPABILITY_INCLUDED != 0
+    }
+
+    pub fn object_included(&self) -> bool {
+        self.0 & FRAME_CONTROL_OBJECT_INCLUDED != 0
+    }
+
+    pub fn is_mesh(&self) -> bool {
+        self.0 & FRAME_CONTROL_MESH != 0
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.0 & FRAME_CONTROL_REGISTERED != 0
+    }
+
+    pub fn is_solicited(&self) -> bool {
+        self.0 & FRAME_CONTROL_SOLICITED != 0
+    }
+
+    pub fn auth_mode(&self) -> u8 {
+        ((self.0 >> 10) & 0b11) as u8
+    }
+
+    pub fn version(&self) -> u8 {
+        (self.0 >> 12) as u8
+    }
+
+    /// Versions 2 and 3 use the legacy encryption scheme
+    pub fn is_legacy(&self) -> bool {
+        self.version() < 4
+    }
+}
+
+/// A MiBeacon frame split into its fields, borrowing the object bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiBeaconFrame<'a> {
+    pub frame_control: FrameControl,
+    pub product_id: u16,
+    pub frame_counter: u8,
+    /// Device MAC in display order (the frame carries it reversed)
+    pub mac: Option<[u8; 6]>,
+    pub capability: Option<u8>,
+    pub io_capability: Option<u16>,
+    /// Object bytes, still encrypted when `frame_control.is_encrypted()`
+    pub object: &'a [u8],
+    pub ext_counter: Option<[u8; EXT_COUNTER_LEN]>,
+    /// AES-CCM tag, not transmitted by legacy frames
+    pub mic: Option<[u8; MIC_LEN]>,
+}
+
+/// Parse MiBeacon service data, starting at the frame control field
+pub fn parse_mibeacon(data: &[u8]) -> Result<MiBeaconFrame<'_>, DecodeError> {
+    if data.len() < 5 {
+        return Err(DecodeError::FrameTooShort);
+    }
+
+    let frame_control = FrameControl(u16::from_le_bytes([data[0], data[1]]));
+    let product_id = u16::from_le_bytes([data[2], data[3]]);
+    let frame_counter = data[4];
+    let mut rest = &data[5..];
+
+    let mut mac = None;
+    if frame_control.mac_included() {
+        let (bytes, tail) = split(rest, 6)?;
+        let mut m = [0u8; 6];
+        for (dst, src) in m.iter_mut().zip(bytes.iter().rev()) {
+            *dst = *src;
+        }
+        mac = Some(m);
+        rest = tail;
+    }
+
+    let mut capability = None;
+    let mut io_capability = None;
+    if frame_control.capability_included() {
+        let (bytes, tail) = split(rest, 1)?;
+        capability = Some(bytes[0]);
+        rest = tail;
+
+        if bytes[0] & CAPABILITY_IO != 0 {
+            let (bytes, tail) = split(rest, 2)?;
+            io_capability = Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+            rest = tail;
+        }
+    }
+
+    let mut ext_counter = None;
+    let mut mic = None;
+    if frame_control.is_encrypted() && frame_control.object_included() {
+        let trailer_len = if frame_control.is_legacy() {
+            EXT_COUNTER_LEN + LEGACY_TRAILER_LEN
+        } else {
+            EXT_COUNTER_LEN + MIC_LEN
+        };
+        if rest.len() < trailer_len {
+            return Err(DecodeError::FrameTooShort);
+        }
+        let (object, trailer) = rest.split_at(rest.len() - trailer_len);
+        ext_counter = Some([trailer[0], trailer[1], trailer[2]]);
+        if !frame_control.is_legacy() {
+            mic = Some([trailer[3], trailer[4], trailer[5], trailer[6]]);
+        }
+        rest = object;
+    }
+
+    let object = if frame_control.object_included() {
+        rest
+    } else {
+        &[]
+    };
+
+    Ok(MiBeaconFrame {
+        frame_control,
+        product_id,
+        frame_counter,
+        mac,
+        capability,
+        io_capability,
+        object,
+        ext_counter,
+        mic,
+    })
+}
+
+impl MiBeaconFrame<'_> {
+    /// AES-CCM nonce of MiBeacon v4/v5: reversed MAC, product id, frame counter, ext. counter
+    pub fn nonce(&self) -> Option<[u8; 12]> {
+        let mac = self.mac?;
+        let ext_counter = self.ext_counter?;
+        let product_id = self.product_id.to_le_bytes();
+
+        Some([
+            mac[5],
+            mac[4],
+            mac[3],
+            mac[2],
+            mac[1],
+            mac[0],
+            product_id[0],
+            product_id[1],
+            self.frame_counter,
+            ext_counter[0],
+            ext_counter[1],
+            ext_counter[2],
+        ])
+    }
+
+    /// AES-CCM nonce of legacy frames: frame ctrl, product id, frame counter, ext. counter, 5 bytes of reversed MAC
+    pub fn legacy_nonce(&self) -> Option<[u8; 13]> {
+        let mac = self.mac?;
+        let ext_counter = self.ext_counter?;
+        let frame_control = self.frame_control.0.to_le_bytes();
+        let product_id = self.product_id.to_le_bytes();
+
+        Some([
+            frame_control[0],
+            frame_control[1],
+            product_id[0],
+            product_id[1],
+            self.frame_counter,
+            ext_counter[0],
+            ext_counter[1],
+            ext_counter[2],
+            mac[5],
+            mac[4],
+            mac[3],
+            mac[2],
+            mac[1],
+        ])
+    }
+
+    /// Decrypt the object with the device bind key (16 bytes, or 12 bytes for legacy frames)
+    pub fn decrypt(&self, key: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.decrypt_with(&DeviceKey::new(key)?)
+    }
+
+    /// Decrypt the object with an already parsed bind key
+    pub fn decrypt_with(&self, key: &DeviceKey) -> Result<Vec<u8>, DecodeError> {
+        if !self.frame_control.is_encrypted() {
+            return Err(DecodeError::Unencrypted);
+        }
+        if !self.frame_control.object_included() {
+            return Err(DecodeError::NoObject);
+        }
+        if self.frame_control.is_legacy() {
+            return self.decrypt_legacy(key.legacy_cipher()?);
+        }
+
+        let nonce = self.nonce().ok_or(DecodeError::MissingMac)?;
+        let mic = self.mic.ok_or(DecodeError::FrameTooShort)?;
+        let cipher: Aes128Ccm = key.ccm()?;
+
+        let to_decrypt = [self.object, &mic].concat();
+        let payload = Payload {
+            msg: &to_decrypt,
+            aad: &[0x11],
+        };
+
+        cipher
+            .decrypt(GenericArray::from_slice(&nonce), payload)
+            .map_err(|_| DecodeError::TagMismatch)
+    }
+
+    /// Legacy frames carry no tag, the AES-CCM keystream is applied without authentication
+    fn decrypt_legacy(&self, cipher: &Aes128) -> Result<Vec<u8>, DecodeError> {
+        let nonce = self.legacy_nonce().ok_or(DecodeError::MissingMac)?;
+
+        let mut plain = self.object.to_vec();
+        for (i, chunk) in plain.chunks_mut(16).enumerate() {
+            // Counter block : flags (2 bytes counter) | nonce | counter, payload starts at 1
+            let mut block = GenericArray::from([0u8; 16]);
+            block[0] = 0x01;
+            block[1..14].copy_from_slice(&nonce);
+            block[14..].copy_from_slice(&(i as u16 + 1).to_be_bytes());
+            cipher.encrypt_block(&mut block);
+
+            for (byte, key_byte) in chunk.iter_mut().zip(block.iter()) {
+                *byte ^= key_byte;
+            }
+        }
+
+        Ok(plain)
+    }
+}
+
+/// A decoded MiBeacon frame with the product that sent it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedFrame {
+    pub product_id: u16,
+    /// Catalog entry, `None` for products not listed
+    pub product: Option<&'static Product>,
+    /// MAC used to decrypt the frame, display order
+    pub mac: Option<[u8; 6]>,
+    pub object_id: u16,
+    pub measurement: Measurement,
+}
+
+impl DecodedFrame {
+    pub fn model(&self) -> Option<&'static str> {
+        self.product.map(|product| product.model)
+    }
+
+    /// Object type the catalog does not list for the product, a new firmware feature or a
+    /// frame sent under another product id
+    pub fn is_unexpected(&self) -> bool {
+        self.product
+            .is_some_and(|product| !product.sends(self.object_id))
+    }
+}
+
+/// Decode a plain object : id (2 bytes) | length (1 byte) | value
+pub fn decode_object(object: &[u8]) -> Result<Measurement, DecodeError> {
+    if object.len() < 3 {
+        return Err(DecodeError::FrameTooShort);
+    }
+
+    let id = u16::from_le_bytes([object[0], object[1]]);
+    let len = object[2] as usize;
+    let value = object.get(3..3 + len).ok_or(DecodeError::FrameTooShort)?;
+
+    let measurement = match (id, value) {
+        (OBJECT_TEMPERATURE, [a, b]) => {
+            Measurement::Temperature(Temperature::from_decidegrees(i16::from_le_bytes([*a, *b])))
+        }
+        (OBJECT_HUMIDITY, [a, b]) => {
+            Measurement::Humidity(RelativeHumidity::from_decipercent(u16::from_le_bytes([
+                *a, *b,
+            ])))
+        }
+        (OBJECT_ILLUMINANCE, [a, b, c]) => {
+            Measurement::Illuminance(u32::from_le_bytes([*a, *b, *c, 0]))
+        }
+        (OBJECT_MOISTURE, [a]) => Measurement::Moisture(*a),
+        (OBJECT_CONDUCTIVITY, [a, b]) => Measurement::Conductivity(u16::from_le_bytes([*a, *b])),
+        (OBJECT_BATTERY, [a]) | (OBJECT_BATTERY_V2, [a]) => {
+            Measurement::Battery(BatteryPercent::new(*a))
+        }
+        (OBJECT_TEMPERATURE_HUMIDITY, [a, b, c, d]) => Measurement::TemperatureHumidity {
+            temperature: Temperature::from_decidegrees(i16::from_le_bytes([*a, *b])),
+            humidity: RelativeHumidity::from_decipercent(u16::from_le_bytes([*c, *d])),
+        },
+        (OBJECT_FORMALDEHYDE, [a, b]) => Measurement::Formaldehyde(u16::from_le_bytes([*a, *b])),
+        (OBJECT_TEMPERATURE_V2, [a, b, c, d]) => {
+            Measurement::Temperature(Temperature::from_celsius(f32::from_le_bytes([
+                *a, *b, *c, *d,
+            ])))
+        }
+        (OBJECT_HUMIDITY_V2, [a]) => {
+            Measurement::Humidity(RelativeHumidity::from_decipercent(*a as u16 * 10))
+        }
+        (OBJECT_BUTTON, [a, b, press]) => {
+            let press = match press {
+                0 => ButtonPress::Single,
+                1 => ButtonPress::Double,
+                2 => ButtonPress::Long,
+                3 => ButtonPress::Triple,
+                _ => return Err(DecodeError::InvalidValue(id)),
+            };
+            Measurement::Event(Event::Button {
+                index: u16::from_le_bytes([*a, *b]),
+                press,
+            })
+        }
+        (OBJECT_MOTION_ILLUMINANCE, [a, b, c]) => Measurement::Event(Event::Motion {
+            illuminance: u32::from_le_bytes([*a, *b, *c, 0]),
+        }),
+        (OBJECT_NO_MOTION, [a, b, c, d]) => Measurement::Event(Event::NoMotion {
+            seconds: u32::from_le_bytes([*a, *b, *c, *d]),
+        }),
+        (OBJECT_DOOR, [state]) => {
+            let state = match state {
+                0 => DoorState::Open,
+                1 => DoorState::Closed,
+                2 => DoorState::LeftOpen,
+                3 => DoorState::Reset,
+                _ => return Err(DecodeError::InvalidValue(id)),
+            };
+            Measurement::Event(Event::Door(state))
+        }
+        (OBJECT_LIGHT, [light]) => Measurement::Event(Event::Light(*light != 0)),
+        (OBJECT_WATER_LEAK, [wet]) => Measurement::Event(Event::WaterLeak(*wet != 0)),
+        (
+            OBJECT_TEMPERATURE
+            | OBJECT_HUMIDITY
+            | OBJECT_ILLUMINANCE
+            | OBJECT_MOISTURE
+            | OBJECT_CONDUCTIVITY
+            | OBJECT_BATTERY
+            | OBJECT_TEMPERATURE_HUMIDITY
+            | OBJECT_FORMALDEHYDE
+            | OBJECT_BATTERY_V2
+            | OBJECT_TEMPERATURE_V2
+            | OBJECT_HUMIDITY_V2
+            | OBJECT_BUTTON
+            | OBJECT_MOTION_ILLUMINANCE
+            | OBJECT_NO_MOTION
+            | OBJECT_DOOR
+            | OBJECT_LIGHT
+            | OBJECT_WATER_LEAK,
+            _,
+        ) => return Err(DecodeError::InvalidObject(id)),
+        _ => return Err(DecodeError::UnsupportedObject(id)),
+    };
+
+    Ok(measurement)
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), DecodeError> {
+    if data.len() < len {
+        return Err(DecodeError::FrameTooShort);
+    }
+    Ok(data.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn parse_encrypted_frame() {
+        let data = decode_hex("58585B054F5C2D4E38C1A44886C7D7A10000007A54168F").unwrap();
+        let frame = parse_mibeacon(&data).unwrap();
+
+        assert!(frame.frame_control.is_encrypted());
+        assert!(frame.frame_control.mac_included());
+        assert!(!frame.frame_control.capability_included());
+        assert!(frame.frame_control.object_included());
+        assert_eq!(frame.frame_control.version(), 5);
+        assert_eq!(frame.product_id, 0x055B);
+        assert_eq!(frame.frame_counter, 0x4F);
+        assert_eq!(frame.mac, Some([0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C]));
+        assert_eq!(frame.object, &[0x48, 0x86, 0xC7, 0xD7, 0xA1]);
+        assert_eq!(frame.ext_counter, Some([0, 0, 0]));
+        assert_eq!(frame.mic, Some([0x7A, 0x54, 0x16, 0x8F]));
+    }
+
+    #[test]
+    fn parse_capability_and_io() {
+        // capability 0x28 announces 2 bytes of IO capability
+        let data = decode_hex("78585B05105C2D4E38C1A428010004100200EC").unwrap();
+        let data = [&data[..], &[0, 0, 0, 1, 2, 3, 4]].concat();
+        let frame = parse_mibeacon(&data).unwrap();
+
+        assert_eq!(frame.capability, Some(0x28));
+        assert_eq!(frame.io_capability, Some(0x0001));
+        assert_eq!(frame.object.len(), 5);
+    }
+
+    #[test]
+    fn decode_objects() {
+        let cases = [
+            (
+                "041002EC00",
+                Measurement::Temperature(Temperature::from_decidegrees(236)),
+            ),
+            (
+                "04100238FF",
+                Measurement::Temperature(Temperature::from_decidegrees(-200)),
+            ),
+            (
+                "0610025802",
+                Measurement::Humidity(RelativeHumidity::from_decipercent(600)),
+            ),
+            ("0A100164", Measurement::Battery(BatteryPercent::new(100))),
+            (
+                "0D1004EC005802",
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(236),
+                    humidity: RelativeHumidity::from_decipercent(600),
+                },
+            ),
+            ("071003E80300", Measurement::Illuminance(1000)),
+            ("0810012A", Measurement::Moisture(42)),
+            ("0910025E01", Measurement::Conductivity(350)),
+            ("0348015F", Measurement::Battery(BatteryPercent::new(95))),
+            (
+                "014C0466663E41",
+                Measurement::Temperature(Temperature::from_decidegrees(119)),
+            ),
+            (
+                "024C0138",
+                Measurement::Humidity(RelativeHumidity::from_decipercent(560)),
+            ),
+            (
+                "011003000002",
+                Measurement::Event(Event::Button {
+                    index: 0,
+                    press: ButtonPress::Long,
+                }),
+            ),
+            (
+                "0F00036E0000",
+                Measurement::Event(Event::Motion { illuminance: 110 }),
+            ),
+            (
+                "17100478000000",
+                Measurement::Event(Event::NoMotion { seconds: 120 }),
+            ),
+            ("19100100", Measurement::Event(Event::Door(DoorState::Open))),
+            (
+                "19100102",
+                Measurement::Event(Event::Door(DoorState::LeftOpen)),
+            ),
+            ("18100100", Measurement::Event(Event::Light(false))),
+            ("14100101", Measurement::Event(Event::WaterLeak(true))),
+        ];
+
+        for (hex, expected) in cases {
+            let object = decode_hex(hex).unwrap();
+            assert_eq!(decode_object(&object), Ok(expected), "{}", hex);
+        }
+
+        // unknown object id
+        assert_eq!(
+            decode_object(&decode_hex("FFFF0100").unwrap()),
+            Err(DecodeError::UnsupportedObject(0xFFFF))
+        );
+
+        // length byte larger than the actual value
+        assert_eq!(
+            decode_object(&decode_hex("041003EC00").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            decode_object(&decode_hex("041001EC").unwrap()),
+            Err(DecodeError::InvalidObject(0x1004))
+        );
+        // value out of the documented range
+        assert_eq!(
+            decode_object(&decode_hex("19100107").unwrap()),
+            Err(DecodeError::InvalidValue(OBJECT_DOOR))
+        );
+        assert_eq!(
+            decode_object(&decode_hex("01100300000A").unwrap()),
+            Err(DecodeError::InvalidValue(OBJECT_BUTTON))
+        );
+    }
+
+    #[test]
+    fn decode_unencrypted() {
+        // LYWSDCGQ, version 2 : 25.4°C 58.4%
+        let data = decode_hex("5020AA01DA67A635342D580D1004FE004802").unwrap();
+        let frame = parse_mibeacon(&data).unwrap();
+
+        assert!(!frame.frame_control.is_encrypted());
+        assert_eq!(frame.frame_control.version(), 2);
+        assert_eq!(frame.mac, Some([0x58, 0x2D, 0x34, 0x35, 0xA6, 0x67]));
+        assert_eq!(frame.decrypt(&[0; 16]), Err(DecodeError::Unencrypted));
+        assert_eq!(
+            decode_object(frame.object),
+            Ok(Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(254),
+                humidity: RelativeHumidity::from_decipercent(584),
+            })
+        );
+    }
+
+    #[test]
+    fn decrypt_legacy() {
+        use crate::key::LEGACY_KEY_PADDING;
+        use ccm::{
+            aead::KeyInit,
+            consts::{U13, U4},
+        };
+
+        let bind_key = decode_hex("b853075158487ca39a5b5ea9").unwrap();
+        let plain = decode_hex("0D1004FE004802").unwrap();
+        let header = decode_hex("5830470314A635342D5834").unwrap();
+        let ext_counter = [0x01, 0x02, 0x03];
+
+        // encrypt with a regular AES-CCM and a 13 bytes nonce, dropping the tag
+        let aes_key = [&bind_key[..6], &LEGACY_KEY_PADDING, &bind_key[6..]].concat();
+        let nonce = [&header[..5], &ext_counter, &header[5..10]].concat();
+        let encrypted = ccm::Ccm::<Aes128, U4, U13>::new_from_slice(&aes_key)
+            .unwrap()
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: &plain,
+                    aad: &[0x11],
+                },
+            )
+            .unwrap();
+
+        let data = [
+            &header,
+            &encrypted[..plain.len()],
+            &ext_counter[..],
+            &[0x00],
+        ]
+        .concat();
+        let frame = parse_mibeacon(&data).unwrap();
+
+        assert!(frame.frame_control.is_legacy());
+        assert_eq!(frame.frame_control.version(), 3);
+        assert_eq!(frame.ext_counter, Some(ext_counter));
+        assert_eq!(frame.mic, None);
+        assert_eq!(frame.decrypt(&bind_key), Ok(plain));
+        assert_eq!(frame.decrypt(&aes_key), Err(DecodeError::InvalidKey));
+    }
+
+    #[test]
+    fn parse_truncated() {
+        assert_eq!(
+            parse_mibeacon(&[0x58, 0x58, 0x5B, 0x05]),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_mibeacon(&decode_hex("58585B054F5C2D4E38").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_mibeacon(&decode_hex("58585B054F5C2D4E38C1A4000000").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(
+            data in vec(any::<u8>(), 0..48),
+            key in prop_oneof![Just(vec![0u8; 16]), Just(vec![0u8; 12])],
+        ) {
+            if let Ok(frame) = parse_mibeacon(&data) {
+                let _ = frame.decrypt(&key);
+                let _ = decode_object(frame.object);
+            }
+            let _ = decode_object(&data);
+        }
+    }
+}
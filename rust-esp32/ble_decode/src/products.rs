@@ -0,0 +1,135 @@
+//! Catalog of the Xiaomi products, by the MiBeacon product id
+//!
+//! Each product lists the object types it is known to send, so objects a model should not
+//! produce can be told apart from new firmware features or misidentified frames.
+
+use crate::mibeacon::{
+    OBJECT_BATTERY, OBJECT_BATTERY_V2, OBJECT_BUTTON, OBJECT_CONDUCTIVITY, OBJECT_DOOR,
+    OBJECT_HUMIDITY, OBJECT_HUMIDITY_V2, OBJECT_ILLUMINANCE, OBJECT_LIGHT, OBJECT_MOISTURE,
+    OBJECT_MOTION_ILLUMINANCE, OBJECT_NO_MOTION, OBJECT_TEMPERATURE, OBJECT_TEMPERATURE_HUMIDITY,
+    OBJECT_TEMPERATURE_V2,
+};
+
+/// A Xiaomi product sending MiBeacon frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Product {
+    pub id: u16,
+    /// Model as printed on the device, e.g. `LYWSD03MMC`
+    pub model: &'static str,
+    pub vendor: &'static str,
+    pub name: &'static str,
+    /// Object types the product sends
+    pub objects: &'static [u16],
+}
+
+impl Product {
+    /// Whether `object` is one of the object types the product sends
+    pub fn sends(&self, object: u16) -> bool {
+        self.objects.contains(&object)
+    }
+}
+
+const THERMOMETER: &[u16] = &[
+    OBJECT_TEMPERATURE,
+    OBJECT_HUMIDITY,
+    OBJECT_TEMPERATURE_HUMIDITY,
+    OBJECT_BATTERY,
+];
+
+pub const PRODUCTS: &[Product] = &[
+    Product {
+        id: 0x0098,
+        model: "HHCCJCY01",
+        vendor: "HHCC",
+        name: "Flower Care",
+        objects: &[
+            OBJECT_TEMPERATURE,
+            OBJECT_ILLUMINANCE,
+            OBJECT_MOISTURE,
+            OBJECT_CONDUCTIVITY,
+            OBJECT_BATTERY,
+        ],
+    },
+    Product {
+        id: 0x0347,
+        model: "CGG1",
+        vendor: "Qingping",
+        name: "Temperature Humidity Monitor",
+        objects: THERMOMETER,
+    },
+    Product {
+        id: 0x0387,
+        model: "MHO-C401",
+        vendor: "Miaomiaoce",
+        name: "E-Ink Thermometer",
+        objects: THERMOMETER,
+    },
+    Product {
+        id: 0x055B,
+        model: "LYWSD03MMC",
+        vendor: "Xiaomi",
+        name: "Mijia Thermometer 2",
+        objects: THERMOMETER,
+    },
+    Product {
+        id: 0x098B,
+        model: "MCCGQ02HL",
+        vendor: "Xiaomi",
+        name: "Door and Window Sensor 2",
+        objects: &[OBJECT_DOOR, OBJECT_LIGHT, OBJECT_BATTERY],
+    },
+    Product {
+        id: 0x0A8D,
+        model: "RTCGQ02LM",
+        vendor: "Xiaomi",
+        name: "Motion Sensor 2",
+        objects: &[
+            OBJECT_MOTION_ILLUMINANCE,
+            OBJECT_NO_MOTION,
+            OBJECT_LIGHT,
+            OBJECT_BUTTON,
+            OBJECT_BATTERY,
+        ],
+    },
+    Product {
+        id: 0x0B48,
+        model: "CGG1",
+        vendor: "Qingping",
+        name: "Temperature Humidity Monitor (encrypted)",
+        objects: THERMOMETER,
+    },
+    Product {
+        id: 0x2832,
+        model: "MJWSD05MMC",
+        vendor: "Xiaomi",
+        name: "Mijia Thermometer 3",
+        objects: &[OBJECT_TEMPERATURE_V2, OBJECT_HUMIDITY_V2, OBJECT_BATTERY_V2],
+    },
+];
+
+/// Catalog entry of a product id, `None` for products not listed
+pub fn product(id: u16) -> Option<&'static Product> {
+    PRODUCTS.iter().find(|product| product.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        let lywsd03mmc = product(0x055B).unwrap();
+        assert_eq!(lywsd03mmc.model, "LYWSD03MMC");
+        assert!(lywsd03mmc.sends(OBJECT_TEMPERATURE));
+        assert!(!lywsd03mmc.sends(OBJECT_MOISTURE));
+
+        assert_eq!(product(0x0098).unwrap().model, "HHCCJCY01");
+        assert!(product(0x0A8D).unwrap().sends(OBJECT_NO_MOTION));
+        assert_eq!(product(0xFFFF), None);
+
+        // one entry per id
+        for (i, product) in PRODUCTS.iter().enumerate() {
+            assert!(PRODUCTS[i + 1..].iter().all(|other| other.id != product.id));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Qingping (ClearGrass) advertisements (service data UUID 0xFDCD)
+//!
+//! Layout of the service data, all multi-byte fields are little-endian:
+//!
+//! ```text
+//! Frame ctrl | Device type | MAC (LE) | Objects: type (1) | length (1) | value
+//!     1      |      1      |    6     |
+//! ```
+//!
+//! Objects: 0x01 temp i16 0.1°C + humi u16 0.1%, 0x02 batt u8 %, 0x07 pressure u16 0.1 hPa,
+//! 0x12 PM2.5 u16 + PM10 u16 µg/m³, 0x13 CO2 u16 ppm. Other types are skipped.
+
+use crate::{BatteryPercent, DecodeError, Measurement, RelativeHumidity, Temperature};
+
+/// 0xFDCD Qingping Technology (Beijing) Co., Ltd.
+pub const QINGPING_SERVICE_UUID: u16 = 0xFDCD;
+
+const HEADER_LEN: usize = 8;
+
+const OBJECT_TEMPERATURE_HUMIDITY: u8 = 0x01;
+const OBJECT_BATTERY: u8 = 0x02;
+const OBJECT_PRESSURE: u8 = 0x07;
+const OBJECT_PM: u8 = 0x12;
+const OBJECT_CO2: u8 = 0x13;
+
+/// A decoded Qingping advertisement
+#[derive(Debug, Clone, PartialEq)]
+pub struct QingpingFrame {
+    pub frame_control: u8,
+    pub device_type: u8,
+    /// Device MAC in display order
+    pub mac: [u8; 6],
+    pub measurements: Vec<Measurement>,
+}
+
+impl QingpingFrame {
+    /// Model of the device type, e.g. `CGP1W`
+    pub fn model(&self) -> Option<&'static str> {
+        match self.device_type {
+            0x07 => Some("CGG1"),
+            0x09 => Some("CGP1W"),
+            0x10 => Some("CGDK2"),
+            _ => None,
+        }
+    }
+}
+
+/// Decode Qingping service data
+pub fn parse_qingping(data: &[u8]) -> Result<QingpingFrame, DecodeError> {
+    if data.len() < HEADER_LEN {
+        return Err(DecodeError::FrameTooShort);
+    }
+    let mac = [data[7], data[6], data[5], data[4], data[3], data[2]];
+
+    let mut measurements = Vec::new();
+    let mut objects = &data[HEADER_LEN..];
+    while let [object_type, len, rest @ ..] = objects {
+        let len = *len as usize;
+        if rest.len() < len {
+            return Err(DecodeError::FrameTooShort);
+        }
+        let (value, next) = rest.split_at(len);
+        objects = next;
+
+        let u16_at = |offset: usize| u16::from_le_bytes([value[offset], value[offset + 1]]);
+        match (*object_type, len) {
+            (OBJECT_TEMPERATURE_HUMIDITY, 4) => {
+                measurements.push(Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(u16_at(0) as i16),
+                    humidity: RelativeHumidity::from_decipercent(u16_at(2)),
+                })
+            }
+            (OBJECT_BATTERY, 1) => {
+                measurements.push(Measurement::Battery(BatteryPercent::new(value[0])))
+            }
+            (OBJECT_PRESSURE, 2) => measurements.push(Measurement::Pressure(u16_at(0) as u32 * 10)),
+            (OBJECT_PM, 4) => {
+                measurements.push(Measurement::Pm25(u16_at(0)));
+                measurements.push(Measurement::Pm10(u16_at(2)));
+            }
+            (OBJECT_CO2, 2) => measurements.push(Measurement::Co2(u16_at(0))),
+            (
+                OBJECT_TEMPERATURE_HUMIDITY
+                | OBJECT_BATTERY
+                | OBJECT_PRESSURE
+                | OBJECT_PM
+                | OBJECT_CO2,
+                _,
+            ) => return Err(DecodeError::InvalidObject(*object_type as u16)),
+            _ => {}
+        }
+    }
+    if !objects.is_empty() {
+        return Err(DecodeError::FrameTooShort);
+    }
+
+    Ok(QingpingFrame {
+        frame_control: data[0],
+        device_type: data[1],
+        mac,
+        measurements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    #[test]
+    fn cgp1w() {
+        let frame =
+            parse_qingping(&decode_hex("0809C46DB018B7E70104EE00FE0102015A07021A27").unwrap())
+                .unwrap();
+
+        assert_eq!(frame.model(), Some("CGP1W"));
+        assert_eq!(frame.mac, [0xE7, 0xB7, 0x18, 0xB0, 0x6D, 0xC4]);
+        assert_eq!(
+            frame.measurements,
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(238),
+                    humidity: RelativeHumidity::from_decipercent(510),
+                },
+                Measurement::Battery(BatteryPercent::new(90)),
+                Measurement::Pressure(100_100),
+            ]
+        );
+    }
+
+    #[test]
+    fn cgdk2() {
+        // negative temperature, packet id (0x0F) skipped
+        let frame = parse_qingping(
+            &decode_hex("8810112233445566010438FF20030F010312040A00140013029001").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(frame.model(), Some("CGDK2"));
+        assert_eq!(
+            frame.measurements,
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(-200),
+                    humidity: RelativeHumidity::from_decipercent(800),
+                },
+                Measurement::Pm25(10),
+                Measurement::Pm10(20),
+                Measurement::Co2(400),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_qingping(&decode_hex("0809C46DB018B7").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_qingping(&decode_hex("0809C46DB018B7E70104EE00").unwrap()),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_qingping(&decode_hex("0809C46DB018B7E702020000").unwrap()),
+            Err(DecodeError::InvalidObject(0x02))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(data in vec(any::<u8>(), 0..32)) {
+            let _ = parse_qingping(&data);
+        }
+    }
+}
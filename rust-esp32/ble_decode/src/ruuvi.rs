@@ -0,0 +1,196 @@
+//! RuuviTag RAWv2 advertisements (manufacturer data of company 0x0499, data format 5)
+//!
+//! Layout of the manufacturer data, all multi-byte fields are big-endian:
+//!
+//! ```text
+//! 5 | temp i16 0.005°C | humi u16 0.0025% | pressure u16 Pa - 50000 | acc x, y, z i16 mG
+//!   | power u16: battery 11 bits mV - 1600, TX power 5 bits 2 dBm - 40
+//!   | movement u8 | seq u16 | MAC (BE)
+//! ```
+//!
+//! Each field has a "not available" value, all bits set or `0x8000` for the signed ones.
+
+use crate::{DecodeError, Measurement, RelativeHumidity, Temperature, Voltage};
+
+/// Ruuvi Innovations Ltd.
+pub const RUUVI_COMPANY_ID: u16 = 0x0499;
+
+const RAWV2_FORMAT: u8 = 5;
+const RAWV2_LEN: usize = 24;
+
+/// A decoded RAWv2 advertisement, `None` for the fields the tag marks as not available
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuuviFrame {
+    pub temperature: Option<Temperature>,
+    pub humidity: Option<RelativeHumidity>,
+    /// Pa
+    pub pressure: Option<u32>,
+    /// mG along the x, y and z axes
+    pub acceleration: [Option<i16>; 3],
+    pub voltage: Option<Voltage>,
+    /// dBm
+    pub tx_power: Option<i8>,
+    pub movement_counter: Option<u8>,
+    pub sequence: Option<u16>,
+    /// Device MAC in display order
+    pub mac: Option<[u8; 6]>,
+}
+
+impl RuuviFrame {
+    /// The fields with a common measurement type
+    pub fn measurements(&self) -> Vec<Measurement> {
+        let mut measurements = Vec::new();
+        match (self.temperature, self.humidity) {
+            (Some(temperature), Some(humidity)) => {
+                measurements.push(Measurement::TemperatureHumidity {
+                    temperature,
+                    humidity,
+                })
+            }
+            (temperature, humidity) => {
+                measurements.extend(temperature.map(Measurement::Temperature));
+                measurements.extend(humidity.map(Measurement::Humidity));
+            }
+        }
+        measurements.extend(self.pressure.map(Measurement::Pressure));
+        measurements.extend(self.voltage.map(Measurement::Voltage));
+        measurements
+    }
+}
+
+/// Decode Ruuvi manufacturer data, without the company id
+pub fn parse_ruuvi(data: &[u8]) -> Result<RuuviFrame, DecodeError> {
+    match data.first() {
+        None => return Err(DecodeError::FrameTooShort),
+        Some(&RAWV2_FORMAT) => {}
+        Some(_) => return Err(DecodeError::UnknownFormat),
+    }
+    if data.len() < RAWV2_LEN {
+        return Err(DecodeError::FrameTooShort);
+    }
+
+    let signed = |offset: usize| {
+        let value = i16::from_be_bytes([data[offset], data[offset + 1]]);
+        (value != i16::MIN).then_some(value)
+    };
+    let unsigned = |offset: usize| {
+        let value = u16::from_be_bytes([data[offset], data[offset + 1]]);
+        (value != u16::MAX).then_some(value)
+    };
+
+    let power = u16::from_be_bytes([data[13], data[14]]);
+    let battery = power >> 5;
+    let tx_power = (power & 0x1F) as i8;
+    let mac = [data[18], data[19], data[20], data[21], data[22], data[23]];
+
+    Ok(RuuviFrame {
+        temperature: signed(1).map(|t| Temperature::from_celsius(t as f32 * 0.005)),
+        humidity: unsigned(3).map(|h| RelativeHumidity::from_percent(h as f32 * 0.0025)),
+        pressure: unsigned(5).map(|p| p as u32 + 50_000),
+        acceleration: [signed(7), signed(9), signed(11)],
+        voltage: (battery != 0x7FF).then(|| Voltage::from_millivolts(battery + 1600)),
+        tx_power: (tx_power != 0x1F).then_some(tx_power * 2 - 40),
+        movement_counter: (data[15] != u8::MAX).then_some(data[15]),
+        sequence: unsigned(16),
+        mac: (mac != [0xFF; 6]).then_some(mac),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    // test vectors of the Ruuvi documentation
+    #[test]
+    fn rawv2() {
+        let frame =
+            parse_ruuvi(&decode_hex("0512FC5394C37C0004FFFC040CAC364200CDCBB8334C884F").unwrap())
+                .unwrap();
+        assert_eq!(
+            frame,
+            RuuviFrame {
+                temperature: Some(Temperature::from_decidegrees(243)),
+                humidity: Some(RelativeHumidity::from_decipercent(535)),
+                pressure: Some(100_044),
+                acceleration: [Some(4), Some(-4), Some(1036)],
+                voltage: Some(Voltage::from_millivolts(2977)),
+                tx_power: Some(4),
+                movement_counter: Some(66),
+                sequence: Some(205),
+                mac: Some([0xCB, 0xB8, 0x33, 0x4C, 0x88, 0x4F]),
+            }
+        );
+        assert_eq!(
+            frame.measurements(),
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(243),
+                    humidity: RelativeHumidity::from_decipercent(535),
+                },
+                Measurement::Pressure(100_044),
+                Measurement::Voltage(Voltage::from_millivolts(2977)),
+            ]
+        );
+
+        let max =
+            parse_ruuvi(&decode_hex("057FFFFFFEFFFE7FFF7FFF7FFFFFDEFEFFFECBB8334C884F").unwrap())
+                .unwrap();
+        assert_eq!(max.temperature, Some(Temperature::from_celsius(163.835)));
+        assert_eq!(max.pressure, Some(115_534));
+        assert_eq!(max.acceleration, [Some(32767); 3]);
+        assert_eq!(max.voltage, Some(Voltage::from_millivolts(3646)));
+        assert_eq!(max.tx_power, Some(20));
+        assert_eq!(max.movement_counter, Some(254));
+        assert_eq!(max.sequence, Some(65534));
+
+        let min =
+            parse_ruuvi(&decode_hex("058001000000008001800180010000000000CBB8334C884F").unwrap())
+                .unwrap();
+        assert_eq!(min.temperature, Some(Temperature::from_celsius(-163.835)));
+        assert_eq!(min.humidity, Some(RelativeHumidity::from_decipercent(0)));
+        assert_eq!(min.pressure, Some(50_000));
+        assert_eq!(min.acceleration, [Some(-32767); 3]);
+        assert_eq!(min.voltage, Some(Voltage::from_millivolts(1600)));
+        assert_eq!(min.tx_power, Some(-40));
+    }
+
+    #[test]
+    fn not_available() {
+        let frame =
+            parse_ruuvi(&decode_hex("058000FFFFFFFF800080008000FFFFFFFFFFFFFFFFFFFFFF").unwrap())
+                .unwrap();
+        assert_eq!(
+            frame,
+            RuuviFrame {
+                temperature: None,
+                humidity: None,
+                pressure: None,
+                acceleration: [None; 3],
+                voltage: None,
+                tx_power: None,
+                movement_counter: None,
+                sequence: None,
+                mac: None,
+            }
+        );
+        assert_eq!(frame.measurements(), vec![]);
+
+        assert_eq!(parse_ruuvi(&[]), Err(DecodeError::FrameTooShort));
+        assert_eq!(parse_ruuvi(&[0x05, 0x12]), Err(DecodeError::FrameTooShort));
+        assert_eq!(
+            parse_ruuvi(&decode_hex("0301FF").unwrap()),
+            Err(DecodeError::UnknownFormat)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(data in vec(any::<u8>(), 0..32)) {
+            if let Ok(frame) = parse_ruuvi(&data) {
+                let _ = frame.measurements();
+            }
+        }
+    }
+}
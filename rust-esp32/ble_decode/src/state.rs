@@ -0,0 +1,315 @@
+//! Latest readings of a sensor, merged from the frames it sends
+//!
+//! MiBeacon sensors send temperature and humidity in separate frames: a `SensorState` keeps the
+//! last value of each field with the time it was received, and derives comfort metrics from the
+//! temperature and humidity pair.
+
+use serde::Serialize;
+
+use crate::{BatteryPercent, Event, Measurement, RelativeHumidity, Temperature, Voltage};
+
+/// A value with the Unix timestamp, in seconds, it was received at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Timestamped<T> {
+    pub value: T,
+    pub timestamp: i64,
+}
+
+/// How the temperature and humidity feel indoors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comfort {
+    /// Below 18°C
+    Cold,
+    /// Above 26°C
+    Hot,
+    /// Below 30% of relative humidity
+    Dry,
+    /// Above 60% of relative humidity
+    Humid,
+    Comfortable,
+}
+
+/// Last known value of each field of a sensor
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SensorState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Timestamped<Temperature>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<Timestamped<RelativeHumidity>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery: Option<Timestamped<BatteryPercent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voltage: Option<Timestamped<Voltage>>,
+    /// lux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub illuminance: Option<Timestamped<u32>>,
+    /// Pa
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<Timestamped<u32>>,
+    /// ppm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub co2: Option<Timestamped<u16>>,
+    /// µg/m³
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pm25: Option<Timestamped<u16>>,
+    /// µg/m³
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pm10: Option<Timestamped<u16>>,
+    /// %
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moisture: Option<Timestamped<u8>>,
+    /// µS/cm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conductivity: Option<Timestamped<u16>>,
+    /// Hundredths of mg/m³
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formaldehyde: Option<Timestamped<u16>>,
+    /// Last button press, door or motion change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<Timestamped<Event>>,
+}
+
+/// The state of a sensor with its derived values, as served to clients
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    #[serde(flatten)]
+    pub state: SensorState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dew_point: Option<Temperature>,
+    /// g/m³
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absolute_humidity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heat_index: Option<Temperature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comfort: Option<Comfort>,
+}
+
+// Magnus formula coefficients over water, -45°C to 60°C
+const MAGNUS_B: f32 = 17.62;
+const MAGNUS_C: f32 = 243.12;
+
+impl SensorState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a measurement received at `timestamp`
+    pub fn update(&mut self, measurement: &Measurement, timestamp: i64) {
+        fn set<T>(field: &mut Option<Timestamped<T>>, value: T, timestamp: i64) {
+            *field = Some(Timestamped { value, timestamp });
+        }
+
+        match *measurement {
+            Measurement::Temperature(t) => set(&mut self.temperature, t, timestamp),
+            Measurement::Humidity(h) => set(&mut self.humidity, h, timestamp),
+            Measurement::TemperatureHumidity {
+                temperature,
+                humidity,
+            } => {
+                set(&mut self.temperature, temperature, timestamp);
+                set(&mut self.humidity, humidity, timestamp);
+            }
+            Measurement::Battery(b) => set(&mut self.battery, b, timestamp),
+            Measurement::Voltage(v) => set(&mut self.voltage, v, timestamp),
+            Measurement::Illuminance(lux) => set(&mut self.illuminance, lux, timestamp),
+            Measurement::Pressure(pa) => set(&mut self.pressure, pa, timestamp),
+            Measurement::Co2(ppm) => set(&mut self.co2, ppm, timestamp),
+            Measurement::Pm25(pm) => set(&mut self.pm25, pm, timestamp),
+            Measurement::Pm10(pm) => set(&mut self.pm10, pm, timestamp),
+            Measurement::Moisture(m) => set(&mut self.moisture, m, timestamp),
+            Measurement::Conductivity(c) => set(&mut self.conductivity, c, timestamp),
+            Measurement::Formaldehyde(f) => set(&mut self.formaldehyde, f, timestamp),
+            Measurement::Event(e) => set(&mut self.event, e, timestamp),
+        }
+    }
+
+    /// Timestamp of the most recent update, `None` before the first one
+    pub fn last_update(&self) -> Option<i64> {
+        [
+            self.temperature.map(|t| t.timestamp),
+            self.humidity.map(|t| t.timestamp),
+            self.battery.map(|t| t.timestamp),
+            self.voltage.map(|t| t.timestamp),
+            self.illuminance.map(|t| t.timestamp),
+            self.pressure.map(|t| t.timestamp),
+            self.co2.map(|t| t.timestamp),
+            self.pm25.map(|t| t.timestamp),
+            self.pm10.map(|t| t.timestamp),
+            self.moisture.map(|t| t.timestamp),
+            self.conductivity.map(|t| t.timestamp),
+            self.formaldehyde.map(|t| t.timestamp),
+            self.event.map(|t| t.timestamp),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+    }
+
+    /// Last temperature in °C and relative humidity in %
+    fn climate(&self) -> Option<(f32, f32)> {
+        Some((
+            self.temperature?.value.celsius(),
+            self.humidity?.value.percent(),
+        ))
+    }
+
+    /// Temperature at which the air would be saturated, `None` for a 0% humidity
+    pub fn dew_point(&self) -> Option<Temperature> {
+        let (t, rh) = self.climate()?;
+        if rh <= 0.0 {
+            return None;
+        }
+
+        let gamma = (rh / 100.0).ln() + MAGNUS_B * t / (MAGNUS_C + t);
+        Some(Temperature::from_celsius(
+            MAGNUS_C * gamma / (MAGNUS_B - gamma),
+        ))
+    }
+
+    /// Water vapour density in g/m³
+    pub fn absolute_humidity(&self) -> Option<f32> {
+        let (t, rh) = self.climate()?;
+
+        // saturation vapour pressure in hPa, times the water gas constant inverse
+        let saturation = 6.112 * (MAGNUS_B * t / (MAGNUS_C + t)).exp();
+        Some(saturation * rh * 2.1674 / (273.15 + t))
+    }
+
+    /// Apparent temperature, NWS Rothfusz regression with its low and high humidity adjustments
+    pub fn heat_index(&self) -> Option<Temperature> {
+        let (_, rh) = self.climate()?;
+        let t = self.temperature?.value.fahrenheit();
+
+        let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+        if (simple + t) / 2.0 < 80.0 {
+            return Some(Temperature::from_fahrenheit(simple));
+        }
+
+        let mut index = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+        }
+        Some(Temperature::from_fahrenheit(index))
+    }
+
+    /// Comfort of the last temperature and humidity, temperature first
+    pub fn comfort(&self) -> Option<Comfort> {
+        let (t, rh) = self.climate()?;
+
+        let comfort = if t < 18.0 {
+            Comfort::Cold
+        } else if t > 26.0 {
+            Comfort::Hot
+        } else if rh < 30.0 {
+            Comfort::Dry
+        } else if rh > 60.0 {
+            Comfort::Humid
+        } else {
+            Comfort::Comfortable
+        };
+        Some(comfort)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.clone(),
+            dew_point: self.dew_point(),
+            absolute_humidity: self.absolute_humidity(),
+            heat_index: self.heat_index(),
+            comfort: self.comfort(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn climate(celsius: f32, percent: f32) -> SensorState {
+        let mut state = SensorState::new();
+        state.update(&Measurement::Temperature(celsius.into()), 10);
+        state.update(&Measurement::Humidity(percent.into()), 20);
+        state
+    }
+
+    #[test]
+    fn merge() {
+        let mut state = SensorState::new();
+        assert_eq!(state.last_update(), None);
+        assert_eq!(state.dew_point(), None);
+
+        state.update(
+            &Measurement::Temperature(Temperature::from_decidegrees(215)),
+            100,
+        );
+        state.update(&Measurement::Battery(BatteryPercent::new(80)), 110);
+        assert_eq!(state.comfort(), None);
+
+        state.update(
+            &Measurement::Humidity(RelativeHumidity::from_decipercent(456)),
+            120,
+        );
+        assert_eq!(
+            state.temperature,
+            Some(Timestamped {
+                value: Temperature::from_decidegrees(215),
+                timestamp: 100
+            })
+        );
+        assert_eq!(state.humidity.unwrap().timestamp, 120);
+        assert_eq!(state.battery.unwrap().value, BatteryPercent::new(80));
+        assert_eq!(state.last_update(), Some(120));
+
+        state.update(
+            &Measurement::TemperatureHumidity {
+                temperature: Temperature::from_decidegrees(220),
+                humidity: RelativeHumidity::from_decipercent(500),
+            },
+            130,
+        );
+        assert_eq!(state.temperature.unwrap().timestamp, 130);
+        assert_eq!(state.humidity.unwrap().timestamp, 130);
+        assert_eq!(state.battery.unwrap().timestamp, 110);
+
+        let json = serde_json::to_value(state.snapshot()).unwrap();
+        assert_eq!(json["temperature"]["value"], 22.0);
+        assert_eq!(json["humidity"]["timestamp"], 130);
+        assert_eq!(json["comfort"], "comfortable");
+        assert!(json.get("co2").is_none());
+
+        state.update(&Measurement::Event(Event::Light(true)), 140);
+        assert_eq!(state.event.unwrap().value, Event::Light(true));
+        assert_eq!(state.last_update(), Some(140));
+    }
+
+    #[test]
+    fn derived_values() {
+        let state = climate(25.0, 60.0);
+        assert_eq!(state.dew_point(), Some(Temperature::from_celsius(16.7)));
+        assert!((state.absolute_humidity().unwrap() - 13.8).abs() < 0.1);
+        assert_eq!(climate(20.0, 0.0).dew_point(), None);
+
+        // NWS table: 90°F and 70% feel like 106°F
+        let hot = climate(32.2, 70.0);
+        assert!((hot.heat_index().unwrap().fahrenheit() - 106.0).abs() < 1.0);
+        // below 80°F the index stays close to the temperature
+        assert!((state.heat_index().unwrap().celsius() - 25.0).abs() < 1.0);
+
+        assert_eq!(climate(17.0, 45.0).comfort(), Some(Comfort::Cold));
+        assert_eq!(climate(28.0, 45.0).comfort(), Some(Comfort::Hot));
+        assert_eq!(climate(21.0, 25.0).comfort(), Some(Comfort::Dry));
+        assert_eq!(climate(21.0, 75.0).comfort(), Some(Comfort::Humid));
+        assert_eq!(climate(21.0, 45.0).comfort(), Some(Comfort::Comfortable));
+    }
+}
@@ -0,0 +1,180 @@
+//! SwitchBot Meter, Meter Plus and Outdoor Meter advertisements
+//!
+//! The service data tells the model and the battery level, the readings come from the
+//! manufacturer data when the device sends it (Outdoor Meter, recent firmwares) or else from the
+//! service data:
+//!
+//! ```text
+//! Service data (0xFD3D)      : type | status | batt 7 bits % | temp decimals 4 bits 0.1°C
+//!                            | temp int 7 bits °C, bit 7 positive | humi 7 bits %, bit 7 °F display
+//! Manufacturer data (0x0969) : MAC (6) | seq | status | same 3 temperature and humidity bytes
+//! ```
+
+use crate::{BatteryPercent, DecodeError, Measurement, RelativeHumidity, Temperature};
+
+/// 0xFD3D Woan Technology (Shenzhen) Co., Ltd.
+pub const SWITCHBOT_SERVICE_UUID: u16 = 0xFD3D;
+/// Woan Technology (Shenzhen) Co., Ltd.
+pub const SWITCHBOT_COMPANY_ID: u16 = 0x0969;
+
+const SERVICE_DATA_READINGS: usize = 3;
+const MANUFACTURER_DATA_READINGS: usize = 8;
+const READINGS_LEN: usize = 3;
+
+const ENCRYPTED: u8 = 0x80;
+const TEMPERATURE_POSITIVE: u8 = 0x80;
+const FAHRENHEIT: u8 = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchBotModel {
+    Meter,
+    MeterPlus,
+    OutdoorMeter,
+}
+
+impl SwitchBotModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwitchBotModel::Meter => "Meter",
+            SwitchBotModel::MeterPlus => "Meter Plus",
+            SwitchBotModel::OutdoorMeter => "Outdoor Meter",
+        }
+    }
+
+    /// Model of the device type byte of the service data, its bit 7 cleared
+    pub fn from_device_type(device_type: u8) -> Option<Self> {
+        match device_type {
+            b'T' => Some(SwitchBotModel::Meter),
+            b'i' => Some(SwitchBotModel::MeterPlus),
+            b'w' => Some(SwitchBotModel::OutdoorMeter),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded SwitchBot meter advertisement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwitchBotFrame {
+    pub model: SwitchBotModel,
+    pub temperature: Temperature,
+    pub humidity: RelativeHumidity,
+    pub battery: BatteryPercent,
+    /// The device displays the temperature in °F, `temperature` is still in °C
+    pub fahrenheit: bool,
+}
+
+impl SwitchBotFrame {
+    pub fn measurements(&self) -> Vec<Measurement> {
+        vec![
+            Measurement::TemperatureHumidity {
+                temperature: self.temperature,
+                humidity: self.humidity,
+            },
+            Measurement::Battery(self.battery),
+        ]
+    }
+}
+
+/// Decode the service data of a SwitchBot meter, along with its manufacturer data without the
+/// company id when advertised
+pub fn parse_switchbot(
+    service_data: &[u8],
+    manufacturer_data: Option<&[u8]>,
+) -> Result<SwitchBotFrame, DecodeError> {
+    let [device_type, _, battery, ..] = *service_data else {
+        return Err(DecodeError::FrameTooShort);
+    };
+    if device_type & ENCRYPTED != 0 {
+        return Err(DecodeError::Encrypted);
+    }
+    let model = SwitchBotModel::from_device_type(device_type).ok_or(DecodeError::UnknownFormat)?;
+
+    let readings = manufacturer_data
+        .and_then(|data| data.get(MANUFACTURER_DATA_READINGS..))
+        .or_else(|| service_data.get(SERVICE_DATA_READINGS..))
+        .and_then(|data| data.get(..READINGS_LEN))
+        .ok_or(DecodeError::FrameTooShort)?;
+
+    let decidegrees = (readings[1] & 0x7F) as i16 * 10 + (readings[0] & 0x0F) as i16;
+    let temperature = if readings[1] & TEMPERATURE_POSITIVE != 0 {
+        decidegrees
+    } else {
+        -decidegrees
+    };
+
+    Ok(SwitchBotFrame {
+        model,
+        temperature: Temperature::from_decidegrees(temperature),
+        humidity: RelativeHumidity::from_decipercent((readings[2] & 0x7F) as u16 * 10),
+        battery: BatteryPercent::new(battery & 0x7F),
+        fahrenheit: readings[2] & FAHRENHEIT != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, option, prelude::*};
+
+    #[test]
+    fn meters() {
+        let meter = parse_switchbot(&decode_hex("540064059630").unwrap(), None).unwrap();
+        assert_eq!(meter.model, SwitchBotModel::Meter);
+        assert_eq!(
+            meter.measurements(),
+            vec![
+                Measurement::TemperatureHumidity {
+                    temperature: Temperature::from_decidegrees(225),
+                    humidity: RelativeHumidity::from_decipercent(480),
+                },
+                Measurement::Battery(BatteryPercent::new(100)),
+            ]
+        );
+        assert!(!meter.fahrenheit);
+
+        // -5.3°C displayed in °F, battery bit 7 set
+        let frozen = parse_switchbot(&decode_hex("6900D70305B7").unwrap(), None).unwrap();
+        assert_eq!(frozen.model, SwitchBotModel::MeterPlus);
+        assert_eq!(frozen.temperature, Temperature::from_decidegrees(-53));
+        assert_eq!(frozen.humidity, RelativeHumidity::from_decipercent(550));
+        assert_eq!(frozen.battery, BatteryPercent::new(87));
+        assert!(frozen.fahrenheit);
+
+        // readings in the manufacturer data only
+        let outdoor = parse_switchbot(
+            &decode_hex("770064").unwrap(),
+            Some(&decode_hex("CB5A8C1E0F426C00048C3A").unwrap()),
+        )
+        .unwrap();
+        assert_eq!(outdoor.model, SwitchBotModel::OutdoorMeter);
+        assert_eq!(outdoor.temperature, Temperature::from_decidegrees(124));
+        assert_eq!(outdoor.humidity, RelativeHumidity::from_decipercent(580));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_switchbot(&decode_hex("770064").unwrap(), None),
+            Err(DecodeError::FrameTooShort)
+        );
+        assert_eq!(
+            parse_switchbot(&decode_hex("D40064059630").unwrap(), None),
+            Err(DecodeError::Encrypted)
+        );
+        assert_eq!(
+            parse_switchbot(&decode_hex("480064").unwrap(), None),
+            Err(DecodeError::UnknownFormat)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(
+            service_data in vec(any::<u8>(), 0..8),
+            manufacturer_data in option::of(vec(any::<u8>(), 0..16)),
+        ) {
+            let _ = parse_switchbot(&service_data, manufacturer_data.as_deref());
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use crate::{
+    ad::find_service_data, mibeacon::MIBEACON_SERVICE_UUID, parse_mibeacon, MiBeaconFrame,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameStatus {
+    /// Counter moved forward since the last accepted frame
+    New,
+    /// Same counter as the last accepted frame, a repeated advertisement
+    Duplicate,
+    /// Counter up to `REPLAY_WINDOW` behind the last accepted frame
+    Replayed,
+    /// Counter further behind the last accepted frame, maybe the device restarted: not accepted
+    /// until `RESET_CONFIRMATION` consecutive counters confirm it
+    Reset,
+}
+
+impl FrameStatus {
+    /// Whether the measurements of the frame can be used, once authenticated
+    pub fn is_accepted(&self) -> bool {
+        *self == FrameStatus::New
+    }
+
+    /// Whether the frame should be decrypted and committed once authenticated: the measurements
+    /// of `Reset` frames are dropped, committing them only confirms the restart
+    pub fn needs_commit(&self) -> bool {
+        matches!(self, FrameStatus::New | FrameStatus::Reset)
+    }
+}
+
+/// Counter steps behind the last accepted frame still seen as a replay rather than a reset
+pub const REPLAY_WINDOW: u32 = 32;
+
+/// Consecutive authenticated counters behind the last accepted frame confirming a device restart,
+/// the last of them being the first accepted frame
+pub const RESET_CONFIRMATION: u32 = 3;
+
+/// Keeps the last frame counter accepted per device to drop repeated and replayed frames
+///
+/// Checking a frame does not change the state: the counter is only remembered by `commit`, to be
+/// called once the frame is decrypted and authenticated, so a forged frame cannot lock a device
+/// out.
+///
+/// Counters are compared with serial number arithmetic so they can wrap around: a counter up to
+/// half the counter range ahead of the last one is new, up to `REPLAY_WINDOW` behind is replayed
+/// and further behind is a sensor restarting from a lower counter (reboot, new battery). A single
+/// old frame replayed must not be taken for a restart: the new sequence is only accepted after
+/// `RESET_CONFIRMATION` consecutive counters, or after `forget`.
+#[derive(Debug, Default, Clone)]
+pub struct FrameTracker {
+    devices: HashMap<[u8; 6], DeviceCounters>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DeviceCounters {
+    last: Counter,
+    /// Last counter of the sequence restarted behind `last`, and its length
+    restart: Option<(Counter, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Counter {
+    value: u32,
+    bits: u32,
+}
+
+impl Counter {
+    fn new(value: u32, bits: u32) -> Self {
+        let bits = bits.clamp(1, 32);
+        Counter {
+            value: value & mask(bits),
+            bits,
+        }
+    }
+
+    /// Frame counter and ext. counter of a MiBeacon frame
+    fn of_frame(frame: &MiBeaconFrame) -> Self {
+        match frame.ext_counter {
+            Some(ext) => Counter::new(
+                u32::from_le_bytes([frame.frame_counter, ext[0], ext[1], ext[2]]),
+                32,
+            ),
+            None => Counter::new(frame.frame_counter as u32, 8),
+        }
+    }
+
+    fn follows(&self, previous: Counter) -> bool {
+        self.bits == previous.bits && self.value == previous.value.wrapping_add(1) & mask(self.bits)
+    }
+}
+
+impl FrameTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classify a `bits` wide counter received from `mac`, without remembering it
+    pub fn check(&self, mac: [u8; 6], counter: u32, bits: u32) -> FrameStatus {
+        self.check_counter(mac, Counter::new(counter, bits))
+    }
+
+    /// Remember the counter of an authenticated frame from `mac`
+    pub fn commit(&mut self, mac: [u8; 6], counter: u32, bits: u32) {
+        self.commit_counter(mac, Counter::new(counter, bits))
+    }
+
+    /// Classify a MiBeacon frame on its frame counter and ext. counter, `addr` being the
+    /// advertiser address used for frames sent without MAC
+    pub fn check_frame(&self, addr: [u8; 6], frame: &MiBeaconFrame) -> FrameStatus {
+        self.check_counter(frame.mac.unwrap_or(addr), Counter::of_frame(frame))
+    }
+
+    /// Remember the counters of an authenticated MiBeacon frame
+    pub fn commit_frame(&mut self, addr: [u8; 6], frame: &MiBeaconFrame) {
+        self.commit_counter(frame.mac.unwrap_or(addr), Counter::of_frame(frame))
+    }
+
+    /// Classify the MiBeacon frame of an advertisement payload, `None` when there is none
+    pub fn check_advertisement(&self, addr: [u8; 6], data: &[u8]) -> Option<FrameStatus> {
+        Some(self.check_frame(addr, &mibeacon_frame(data)?))
+    }
+
+    /// Remember the counters of the authenticated MiBeacon frame of an advertisement payload
+    pub fn commit_advertisement(&mut self, addr: [u8; 6], data: &[u8]) {
+        if let Some(frame) = mibeacon_frame(data) {
+            self.commit_frame(addr, &frame);
+        }
+    }
+
+    /// Drop the state of a device, its next frame is accepted whatever its counter
+    pub fn forget(&mut self, mac: [u8; 6]) {
+        self.devices.remove(&mac);
+    }
+
+    fn check_counter(&self, mac: [u8; 6], counter: Counter) -> FrameStatus {
+        let Some(device) = self.devices.get(&mac) else {
+            return FrameStatus::New;
+        };
+        // a device switching counter width (ext. counter appearing) starts over
+        if device.last.bits != counter.bits {
+            return FrameStatus::New;
+        }
+
+        match compare(device.last.value, counter.value, counter.bits) {
+            FrameStatus::Reset => match device.restart {
+                Some((last, len)) if counter.follows(last) && len + 1 >= RESET_CONFIRMATION => {
+                    FrameStatus::New
+                }
+                _ => FrameStatus::Reset,
+            },
+            status => status,
+        }
+    }
+
+    fn commit_counter(&mut self, mac: [u8; 6], counter: Counter) {
+        match self.check_counter(mac, counter) {
+            FrameStatus::New => {
+                self.devices.insert(
+                    mac,
+                    DeviceCounters {
+                        last: counter,
+                        restart: None,
+                    },
+                );
+            }
+            FrameStatus::Reset => {
+                if let Some(device) = self.devices.get_mut(&mac) {
+                    device.restart = match device.restart {
+                        // repeated advertisement of the same frame
+                        Some((last, len)) if last == counter => Some((last, len)),
+                        Some((last, len)) if counter.follows(last) => Some((counter, len + 1)),
+                        _ => Some((counter, 1)),
+                    };
+                }
+            }
+            FrameStatus::Duplicate | FrameStatus::Replayed => {}
+        }
+    }
+}
+
+fn mibeacon_frame(data: &[u8]) -> Option<MiBeaconFrame<'_>> {
+    parse_mibeacon(find_service_data(data, MIBEACON_SERVICE_UUID)?).ok()
+}
+
+fn mask(bits: u32) -> u32 {
+    if bits >= 32 {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+fn compare(last: u32, counter: u32, bits: u32) -> FrameStatus {
+    let diff = counter.wrapping_sub(last) & mask(bits);
+    let half = 1u32 << (bits - 1);
+    let behind = last.wrapping_sub(counter) & mask(bits);
+
+    if diff == 0 {
+        FrameStatus::Duplicate
+    } else if diff < half {
+        FrameStatus::New
+    } else if behind <= REPLAY_WINDOW {
+        FrameStatus::Replayed
+    } else {
+        FrameStatus::Reset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+    use proptest::{collection::vec, prelude::*};
+
+    const MAC: [u8; 6] = [0xA4, 0xC1, 0x38, 0x4E, 0x2D, 0x5C];
+
+    /// Check a counter and commit it when accepted, as after a successful decryption
+    fn receive(tracker: &mut FrameTracker, counter: u32, bits: u32) -> FrameStatus {
+        let status = tracker.check(MAC, counter, bits);
+        if status.needs_commit() {
+            tracker.commit(MAC, counter, bits);
+        }
+        status
+    }
+
+    #[test]
+    fn sequence() {
+        let mut tracker = FrameTracker::new();
+
+        assert_eq!(receive(&mut tracker, 0x4D, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x4D, 32), FrameStatus::Duplicate);
+        assert_eq!(receive(&mut tracker, 0x4E, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x4D, 32), FrameStatus::Replayed);
+        assert_eq!(receive(&mut tracker, 0x4E, 32), FrameStatus::Duplicate);
+        assert_eq!(tracker.check([0; 6], 0x01, 32), FrameStatus::New);
+
+        tracker.forget(MAC);
+        assert_eq!(receive(&mut tracker, 0x01, 32), FrameStatus::New);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut tracker = FrameTracker::new();
+
+        assert_eq!(receive(&mut tracker, 0xFE, 8), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x01, 8), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0xFF, 8), FrameStatus::Replayed);
+
+        assert_eq!(receive(&mut tracker, u32::MAX, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, u32::MAX, 32), FrameStatus::Replayed);
+    }
+
+    #[test]
+    fn uncommitted_frames() {
+        let mut tracker = FrameTracker::new();
+        assert_eq!(receive(&mut tracker, 0x4D, 32), FrameStatus::New);
+
+        // a forged frame far ahead failing authentication is never committed
+        assert_eq!(tracker.check(MAC, 0x7FFF_0000, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x4E, 32), FrameStatus::New);
+    }
+
+    #[test]
+    fn device_reset() {
+        let mut tracker = FrameTracker::new();
+        assert_eq!(receive(&mut tracker, 0x12_3456, 32), FrameStatus::New);
+
+        assert_eq!(
+            receive(&mut tracker, 0x12_3456 - REPLAY_WINDOW, 32),
+            FrameStatus::Replayed
+        );
+        // restarted from 0 after a new battery, accepted on the third consecutive counter
+        assert_eq!(receive(&mut tracker, 0, 32), FrameStatus::Reset);
+        assert_eq!(receive(&mut tracker, 0, 32), FrameStatus::Reset);
+        assert_eq!(receive(&mut tracker, 1, 32), FrameStatus::Reset);
+        assert_eq!(receive(&mut tracker, 2, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 3, 32), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x12_3456, 32), FrameStatus::New);
+
+        // 8 bits counters
+        assert_eq!(receive(&mut tracker, 0x60, 8), FrameStatus::New);
+        assert_eq!(receive(&mut tracker, 0x40, 8), FrameStatus::Replayed);
+        assert_eq!(receive(&mut tracker, 0x01, 8), FrameStatus::Reset);
+        assert_eq!(receive(&mut tracker, 0x02, 8), FrameStatus::Reset);
+        assert_eq!(receive(&mut tracker, 0x03, 8), FrameStatus::New);
+    }
+
+    #[test]
+    fn replayed_old_frames() {
+        let mut tracker = FrameTracker::new();
+        assert_eq!(receive(&mut tracker, 0x1000, 32), FrameStatus::New);
+
+        // old frames out of sequence never confirm a restart
+        for counter in [0x10, 0x20, 0x11, 0x13, 0x20, 0x21] {
+            assert_eq!(receive(&mut tracker, counter, 32), FrameStatus::Reset);
+        }
+        assert_eq!(receive(&mut tracker, 0x1001, 32), FrameStatus::New);
+
+        // unless the device is forgotten
+        tracker.forget(MAC);
+        assert_eq!(receive(&mut tracker, 0x10, 32), FrameStatus::New);
+    }
+
+    #[test]
+    fn advertisements() {
+        let mut tracker = FrameTracker::new();
+        let frame = |counter: &str, ext: &str| {
+            decode_hex(&format!(
+                "0201061A1695FE58585B05{}5C2D4E38C1A44886C7D7A1{}7A54168F",
+                counter, ext
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            tracker.check_advertisement(MAC, &frame("FF", "000000")),
+            Some(FrameStatus::New)
+        );
+        tracker.commit_advertisement(MAC, &frame("FF", "000000"));
+        assert_eq!(
+            tracker.check_advertisement(MAC, &frame("FF", "000000")),
+            Some(FrameStatus::Duplicate)
+        );
+        // frame counter wrapping, ext. counter incremented
+        assert_eq!(
+            tracker.check_advertisement(MAC, &frame("00", "010000")),
+            Some(FrameStatus::New)
+        );
+        tracker.commit_advertisement(MAC, &frame("00", "010000"));
+        assert_eq!(
+            tracker.check_advertisement(MAC, &frame("FF", "000000")),
+            Some(FrameStatus::Replayed)
+        );
+        assert_eq!(
+            tracker.check_advertisement(MAC, &decode_hex("020106").unwrap()),
+            None
+        );
+
+        // frames without MAC are tracked by the advertiser address
+        let without_mac = decode_hex("0E1695FE40505B0501071003640000").unwrap();
+        tracker.commit_advertisement(MAC, &without_mac);
+        assert_eq!(
+            tracker.check_advertisement(MAC, &without_mac),
+            Some(FrameStatus::Duplicate)
+        );
+        assert_eq!(
+            tracker.check_advertisement([0; 6], &without_mac),
+            Some(FrameStatus::New)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics(
+            data in vec(any::<u8>(), 0..48),
+            counter in any::<u32>(),
+            bits in any::<u32>(),
+        ) {
+            let mut tracker = FrameTracker::new();
+            let _ = tracker.check_advertisement(MAC, &data);
+            tracker.commit_advertisement(MAC, &data);
+            tracker.commit(MAC, counter, bits);
+            let _ = tracker.check(MAC, counter.wrapping_add(1), bits);
+            let _ = tracker.check(MAC, counter.wrapping_sub(REPLAY_WINDOW + 1), bits);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Physical quantities decoded from the frames
+//!
+//! Values keep the integer resolution sent by the sensors and are serialized as
+//! floats in their display unit (°C, %, V).
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Temperature in tenths of °C
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "f32", into = "f32")]
+pub struct Temperature(i16);
+
+impl Temperature {
+    pub const fn from_decidegrees(decidegrees: i16) -> Self {
+        Temperature(decidegrees)
+    }
+
+    pub fn from_celsius(celsius: f32) -> Self {
+        Temperature((celsius * 10.0).round() as i16)
+    }
+
+    /// Hundredths of °C, rounded half away from zero
+    pub fn from_centidegrees(centidegrees: i16) -> Self {
+        let value = centidegrees as i32;
+        Temperature(((value + 5 * value.signum()) / 10) as i16)
+    }
+
+    pub fn from_fahrenheit(fahrenheit: f32) -> Self {
+        Self::from_celsius((fahrenheit - 32.0) / 1.8)
+    }
+
+    pub const fn decidegrees(&self) -> i16 {
+        self.0
+    }
+
+    pub fn celsius(&self) -> f32 {
+        self.0 as f32 / 10.0
+    }
+
+    pub fn fahrenheit(&self) -> f32 {
+        self.celsius() * 1.8 + 32.0
+    }
+}
+
+impl From<f32> for Temperature {
+    fn from(celsius: f32) -> Self {
+        Self::from_celsius(celsius)
+    }
+}
+
+impl From<Temperature> for f32 {
+    fn from(temperature: Temperature) -> Self {
+        temperature.celsius()
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}°C", self.celsius())
+    }
+}
+
+/// Relative humidity in tenths of %
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "f32", into = "f32")]
+pub struct RelativeHumidity(u16);
+
+impl RelativeHumidity {
+    pub const fn from_decipercent(decipercent: u16) -> Self {
+        RelativeHumidity(decipercent)
+    }
+
+    pub fn from_percent(percent: f32) -> Self {
+        RelativeHumidity((percent * 10.0).round() as u16)
+    }
+
+    /// Hundredths of %, rounded half up
+    pub fn from_centipercent(centipercent: u16) -> Self {
+        RelativeHumidity(((centipercent as u32 + 5) / 10) as u16)
+    }
+
+    pub const fn decipercent(&self) -> u16 {
+        self.0
+    }
+
+    pub fn percent(&self) -> f32 {
+        self.0 as f32 / 10.0
+    }
+}
+
+impl From<f32> for RelativeHumidity {
+    fn from(percent: f32) -> Self {
+        Self::from_percent(percent)
+    }
+}
+
+impl From<RelativeHumidity> for f32 {
+    fn from(humidity: RelativeHumidity) -> Self {
+        humidity.percent()
+    }
+}
+
+impl fmt::Display for RelativeHumidity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}%", self.percent())
+    }
+}
+
+/// Battery level in %
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BatteryPercent(u8);
+
+impl BatteryPercent {
+    pub const fn new(percent: u8) -> Self {
+        BatteryPercent(percent)
+    }
+
+    pub const fn percent(&self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Display for BatteryPercent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// Voltage in mV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "f32", into = "f32")]
+pub struct Voltage(u16);
+
+impl Voltage {
+    pub const fn from_millivolts(millivolts: u16) -> Self {
+        Voltage(millivolts)
+    }
+
+    pub fn from_volts(volts: f32) -> Self {
+        Voltage((volts * 1000.0).round() as u16)
+    }
+
+    pub const fn millivolts(&self) -> u16 {
+        self.0
+    }
+
+    pub fn volts(&self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+impl From<f32> for Voltage {
+    fn from(volts: f32) -> Self {
+        Self::from_volts(volts)
+    }
+}
+
+impl From<Voltage> for f32 {
+    fn from(voltage: Voltage) -> Self {
+        voltage.volts()
+    }
+}
+
+impl fmt::Display for Voltage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3}V", self.volts())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperature() {
+        let freezer = Temperature::from_decidegrees(-185);
+
+        assert_eq!(freezer.celsius(), -18.5);
+        assert!((freezer.fahrenheit() - -1.3).abs() < 0.001);
+        assert_eq!(freezer.to_string(), "-18.5°C");
+        assert_eq!(Temperature::from_fahrenheit(-1.3), freezer);
+        assert_eq!(Temperature::from_decidegrees(236).to_string(), "23.6°C");
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(RelativeHumidity::from_decipercent(568).to_string(), "56.8%");
+        assert_eq!(BatteryPercent::new(80).to_string(), "80%");
+        assert_eq!(Voltage::from_millivolts(2981).to_string(), "2.981V");
+    }
+
+    #[test]
+    fn serde() {
+        assert_eq!(
+            serde_json::to_string(&Temperature::from_decidegrees(-52)).unwrap(),
+            "-5.2"
+        );
+        assert_eq!(
+            serde_json::from_str::<Temperature>("-5.2").unwrap(),
+            Temperature::from_decidegrees(-52)
+        );
+        assert_eq!(
+            serde_json::from_str::<RelativeHumidity>("56.8").unwrap(),
+            RelativeHumidity::from_decipercent(568)
+        );
+        assert_eq!(
+            serde_json::to_string(&BatteryPercent::new(80)).unwrap(),
+            "80"
+        );
+        assert_eq!(
+            serde_json::to_string(&Voltage::from_millivolts(2981)).unwrap(),
+            "2.981"
+        );
+    }
+}
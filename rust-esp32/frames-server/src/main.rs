@@ -2,12 +2,17 @@
 //!
 //! A simple example integrating juniper in Actix Web
 
+use std::collections::HashMap;
 use std::env;
-use std::{io, sync::Arc};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
 
 use actix_cors::Cors;
 use actix_web::{middleware, web::Data, App, HttpServer};
-use services_rest::create_frame;
+use ble_decode::{DecoderRegistry, Decryptor, SensorState};
+use services_rest::{create_frame, get_states};
 
 mod schema;
 use crate::schema::create_schema;
@@ -25,6 +30,10 @@ pub mod base64;
 pub struct AppState {
     schema: Arc<schema::Schema>,
     db_pool: SqlitePool,
+    /// Decoders of the frame payloads, with the keys of `DEVICES`
+    decoders: DecoderRegistry,
+    /// Latest readings per sensor name, shared by the workers
+    states: Arc<Mutex<HashMap<String, SensorState>>>,
 }
 
 #[actix_web::main]
@@ -43,17 +52,29 @@ async fn main() -> io::Result<()> {
         .await
         .expect(format!("Failed to connect to database: {}", &db_url).as_str());
 
+    let decryptor = match env::var("DEVICES") {
+        Ok(path) => Decryptor::from_path(&path)
+            .unwrap_or_else(|e| panic!("Failed to load devices {}: {}", path, e)),
+        Err(_) => Decryptor::empty(),
+    };
+    let decoders = DecoderRegistry::with_defaults(&decryptor);
+
+    let states = Arc::new(Mutex::new(HashMap::new()));
+
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
             .app_data(Data::new(AppState {
                 schema: schema.clone(),
                 db_pool: pool.clone(),
+                decoders: decoders.clone(),
+                states: states.clone(),
             }))
             .service(graphql)
             .service(graphql_playground)
             .service(get_frames)
             .service(create_frame)
+            .service(get_states)
             // the graphiql UI requires CORS to be enabled
             .wrap(Cors::permissive())
             .wrap(middleware::Logger::default())
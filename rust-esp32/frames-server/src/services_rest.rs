@@ -1,7 +1,10 @@
 use crate::base64::base64;
 use crate::AppState;
-use actix_web::{post, route, web, HttpResponse, Responder};
+use actix_web::{get, post, route, web, HttpResponse, Responder};
+use ble_decode::{parse_mac, Measurement, RelativeHumidity, Snapshot, Temperature};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[route("/frames", method = "GET")]
 pub async fn get_frames() -> impl Responder {
@@ -14,6 +17,8 @@ struct CreateFrameRequest {
     name: String,
     mac: String,
     temperature: f32,
+    #[serde(default)]
+    humidity: Option<f32>,
     #[serde(with = "base64")]
     payload: Vec<u8>,
 }
@@ -40,5 +45,43 @@ pub async fn create_frame(
         new_frame.id, data.name, data.temperature
     );
 
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    // the fields sent along the payload, when no decoder understands it
+    let mut measurements = st
+        .decoders
+        .decode_advertisement(parse_mac(&data.mac), &data.payload)
+        .unwrap_or_default();
+    if measurements.is_empty() {
+        measurements.push(Measurement::Temperature(Temperature::from_celsius(
+            data.temperature,
+        )));
+        measurements.extend(
+            data.humidity
+                .map(|humidity| Measurement::Humidity(RelativeHumidity::from_percent(humidity))),
+        );
+    }
+
+    let mut states = st.states.lock().unwrap();
+    let state = states.entry(data.name.clone()).or_default();
+    for measurement in &measurements {
+        state.update(measurement, timestamp);
+    }
+
     HttpResponse::Ok().json(new_frame.id)
 }
+
+/// Latest readings per sensor name, with dew point, heat index and comfort
+#[get("/states")]
+pub async fn get_states(st: web::Data<AppState>) -> impl Responder {
+    let states: HashMap<String, Snapshot> = st
+        .states
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, state)| (name.clone(), state.snapshot()))
+        .collect();
+    HttpResponse::Ok().json(states)
+}
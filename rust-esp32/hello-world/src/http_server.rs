@@ -14,6 +14,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use ble_decode::{SensorState, Snapshot, Temperature};
+
 use crate::{rgb::Rgb, rmt_neopixel::neopixel};
 
 static INDEX_HTML: &str = include_str!("http_server_page.html");
@@ -32,6 +34,13 @@ struct FormData<'a> {
     color: &'a str,
 }
 
+/// Temperature history and latest readings, with derived comfort values, per room
+#[derive(Serialize)]
+struct Rooms<'a> {
+    history: &'a HashMap<String, Vec<(i64, Temperature)>>,
+    state: HashMap<&'a String, Snapshot>,
+}
+
 #[derive(Serialize)]
 struct Debug {
     free_heap: usize,
@@ -39,7 +48,8 @@ struct Debug {
 
 pub fn create_http_server(
     rgb_handler: Arc<Mutex<TxRmtDriver<'static>>>,
-    history: Arc<Mutex<HashMap<String, Vec<(i64, u16)>>>>,
+    history: Arc<Mutex<HashMap<String, Vec<(i64, Temperature)>>>>,
+    states: Arc<Mutex<HashMap<String, SensorState>>>,
 ) -> anyhow::Result<EspHttpServer<'static>> {
     let server_configuration = esp_idf_svc::http::server::Configuration {
         stack_size: STACK_SIZE,
@@ -64,9 +74,18 @@ pub fn create_http_server(
     })?;
 
     server.fn_handler("/temp", Method::Get, move |req| {
-        let data = &*history.lock().unwrap();
-
-        let b = serde_json::to_string(data).unwrap();
+        let history = &*history.lock().unwrap();
+        let states = &*states.lock().unwrap();
+
+        let rooms = Rooms {
+            history,
+            state: states
+                .iter()
+                .map(|(room, state)| (room, state.snapshot()))
+                .collect(),
+        };
+
+        let b = serde_json::to_string(&rooms).unwrap();
         // dbg!(&b);
         req.into_ok_response()?.write_all(b.as_bytes()).map(|_| ())
     })?;
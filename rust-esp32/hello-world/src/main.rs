@@ -29,7 +29,7 @@ use rgb::Rgb;
 use rmt_neopixel::neopixel;
 use serde::Serialize;
 
-use ble_decode::Decryptor;
+use ble_decode::{DecoderRegistry, Decryptor, FrameTracker, SensorState, Temperature};
 
 #[macro_use]
 extern crate dotenv_codegen;
@@ -93,13 +93,23 @@ fn main() -> anyhow::Result<()> {
     let rgb_handler2 = rgb_handler.clone();
 
     // 30 days in seconds : 2_592_000 => we may try u32 4_294_967_295u32
-    // Keep the temperature as u8
-    let temp_history: HashMap<String, Vec<(i64, u16)>> = HashMap::new();
+    // Temperature is kept in tenths of °C and serialized as °C
+    let temp_history: HashMap<String, Vec<(i64, Temperature)>> = HashMap::new();
 
     let history_arc = Arc::new(Mutex::new(temp_history));
     let history_arc2 = history_arc.clone();
 
-    let _http_server = create_http_server(rgb_handler, history_arc)?;
+    // Latest readings of each room, temperature and humidity come in separate frames
+    let states_arc: Arc<Mutex<HashMap<String, SensorState>>> = Arc::new(Mutex::new(HashMap::new()));
+    let states_arc2 = states_arc.clone();
+
+    let _http_server = create_http_server(rgb_handler, history_arc, states_arc)?;
+
+    // Sensors repeat each frame several times, keep the counters across scans
+    let mut frame_tracker = FrameTracker::new();
+    // Parse the registry and keys once, not for each advertisement
+    let devices = Decryptor::from_embedded()?;
+    let decoders = DecoderRegistry::with_defaults(&devices);
 
     loop {
         let wifi_info = wifi.is_connected();
@@ -113,7 +123,14 @@ fn main() -> anyhow::Result<()> {
             Err(e) => error!("Wifi error: {}", e),
         };
 
-        block_on(run_ble_scan(&rgb_handler2, &history_arc2));
+        block_on(run_ble_scan(
+            &rgb_handler2,
+            &history_arc2,
+            &states_arc2,
+            &mut frame_tracker,
+            &devices,
+            &decoders,
+        ));
     }
 
     Ok(())
@@ -121,7 +138,11 @@ fn main() -> anyhow::Result<()> {
 
 async fn run_ble_scan(
     rgb_handler: &Arc<Mutex<TxRmtDriver<'static>>>,
-    history_arc: &Arc<Mutex<HashMap<String, Vec<(i64, u16)>>>>,
+    history_arc: &Arc<Mutex<HashMap<String, Vec<(i64, Temperature)>>>>,
+    states_arc: &Arc<Mutex<HashMap<String, SensorState>>>,
+    frame_tracker: &mut FrameTracker,
+    devices: &Decryptor,
+    decoders: &DecoderRegistry,
 ) {
     info!("Start BLE scan!");
 
@@ -137,11 +158,14 @@ async fn run_ble_scan(
              data: esp32_nimble::BLEAdvertisedData<&[u8]>| {
                 // info!("Advertised Device: ({:?}, {:?})", device, data);
 
-                let room_option = match device.addr().to_string().as_str() {
-                    "A4:C1:38:4E:2D:5C" => Some("Salon"),
-                    "A4:C1:38:CD:F2:86" => Some("Chambre"),
-                    "A4:C1:38:D7:70:32" => Some("Bébé"),
-                    _ => None,
+                // the decoders read the plain frames of any sensor in range, only keep ours
+                let Some(registered) = devices.device(device.addr().as_be_bytes()) else {
+                    return None;
+                };
+                let room = if registered.room.is_empty() {
+                    registered.mac.as_str()
+                } else {
+                    registered.room.as_str()
                 };
 
                 if device.adv_type() == AdvType::Ind {
@@ -161,14 +185,46 @@ async fn run_ble_scan(
                     //         .join(" ")
                     // );
 
-                    let decryptor = Decryptor::new();
+                    let addr = device.addr().as_be_bytes();
+                    let status = frame_tracker.check_advertisement(addr, data.payload());
+                    if let Some(status) = status {
+                        if !status.needs_commit() {
+                            info!("Skip {:?} frame from {}", status, room);
+                            return None;
+                        }
+                    }
+
+                    // whatever the brand, the advertiser address decrypts the frames sent
+                    // without MAC
+                    let measurements =
+                        match decoders.decode_advertisement(Some(addr), data.payload()) {
+                            Ok(measurements) => {
+                                // only authenticated frames move the counter forward
+                                frame_tracker.commit_advertisement(addr, data.payload());
+                                measurements
+                            }
+                            Err(_) => Vec::new(),
+                        };
+
+                    // a possible device restart, dropped until confirmed by the next frames
+                    if status.is_some_and(|status| !status.is_accepted()) {
+                        info!("Skip {:?} frame from {}", status, room);
+                        return None;
+                    }
 
-                    if let Some(temp) = decryptor.decode_frame_data(data.payload()) {
-                        info!(
-                            "Temperature {:?} : {:.1}°C",
-                            room_option,
-                            (temp as f32) * 0.1
-                        );
+                    if !measurements.is_empty() {
+                        let mut states = states_arc.lock().unwrap();
+                        let state = states.entry(room.to_string()).or_default();
+                        for measurement in &measurements {
+                            state.update(measurement, unix_timestamp());
+                        }
+                    }
+
+                    if let Some(temp) = measurements
+                        .iter()
+                        .find_map(|measurement| measurement.temperature())
+                    {
+                        info!("Temperature {} : {}", room, temp);
 
                         let rgb_handler2 = rgb_handler.clone();
                         thread::spawn(move || {
@@ -178,7 +234,7 @@ async fn run_ble_scan(
                             neopixel(Rgb::new(0, 0, 5), &mut tx).unwrap();
                         });
 
-                        let room_name = room_option.unwrap().to_string();
+                        let room_name = room.to_string();
 
                         let mut history = history_arc.lock().unwrap();
 
@@ -188,10 +244,7 @@ async fn run_ble_scan(
 
                         if history.contains_key(&room_name) {
                             let history_entry = history.get_mut(&room_name).unwrap();
-                            let unix_timestamp = std::time::SystemTime::now()
-                                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                                .expect("Unable to get unixtimestamp")
-                                .as_secs() as i64;
+                            let unix_timestamp = unix_timestamp();
 
                             let mut is_new_value = true;
                             let history_len = history_entry.len();
@@ -226,7 +279,7 @@ async fn run_ble_scan(
                         // let send = post_request(
                         //     &mut client,
                         //     BLEAdvertisedData {
-                        //         name: room_option.unwrap().to_string(),
+                        //         name: room.to_string(),
                         //         temperature: temp,
                         //         mac: device.addr().to_string(),
                         //         payload: data.payload().to_vec(),
@@ -238,7 +291,7 @@ async fn run_ble_scan(
                         // if send.is_err() {
                         //     error!(
                         //         "Unable to send temperature {} : {}°C",
-                        //         room_option.unwrap().to_string(),
+                        //         room.to_string(),
                         //         temp
                         //     );
                         // }
@@ -253,6 +306,13 @@ async fn run_ble_scan(
     info!("Scan end");
 }
 
+fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .expect("Unable to get unixtimestamp")
+        .as_secs() as i64
+}
+
 fn connect_wifi(wifi: &mut BlockingWifi<EspWifi<'static>>) -> anyhow::Result<()> {
     let wifi_configuration: Configuration = Configuration::Client(ClientConfiguration {
         ssid: SSID.try_into().unwrap(),